@@ -0,0 +1,204 @@
+//! Status output for i3bar, i3blocks or polybar.
+//!
+//! `i3-mgr bar` speaks the i3bar protocol: a header, then an endless JSON array with one array
+//! of blocks per update, while click events come in on stdin. `i3-mgr bar --plain` prints one
+//! line per update instead, for the `persist` mode of i3blocks or polybar's `tail = true`.
+
+use crate::config::Settings;
+use crate::events;
+use crate::popup;
+use crate::prelude::*;
+use crate::shutils::{i3_cmd, quoted};
+use crate::window::Snapshot;
+use crate::workspace::{sort_workspaces, WorkspaceSort};
+use serde_json::Value;
+use std::io::{BufRead, Write};
+
+/// A block of the i3bar protocol
+#[derive(Serialize, Debug, PartialEq)]
+struct BarBlock {
+    /// What the block shows, to know what got clicked
+    name: &'static str,
+    /// The con id of the focused window or the name of the workspace
+    instance: String,
+    full_text: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    urgent: bool,
+}
+
+/// A click event sent by i3bar
+#[derive(Deserialize, Debug)]
+struct Click {
+    name: String,
+    #[serde(default)]
+    instance: String,
+    button: u8,
+}
+
+/// The blocks showing the state of the tree: the focused window, every workspace with its
+/// window count and the number of urgent workspaces.
+fn blocks(snapshot: &Snapshot, sort: WorkspaceSort) -> Vec<BarBlock> {
+    let mut blocks = vec![];
+    if let Some(focused) = &snapshot.focused {
+        blocks.push(BarBlock {
+            name: "focused",
+            instance: focused.id.to_string(),
+            full_text: focused.name_str(),
+            urgent: false,
+        });
+    }
+
+    let counts: HashMap<String, usize> = snapshot
+        .workspace_windows()
+        .into_iter()
+        .map(|(workspace, windows)| (workspace, windows.len()))
+        .collect();
+    let mut workspaces = snapshot.workspaces.clone();
+    sort_workspaces(
+        &mut workspaces,
+        sort,
+        |name| counts.get(name).copied().unwrap_or(0),
+        |_| None,
+    );
+    for workspace in &workspaces {
+        blocks.push(BarBlock {
+            name: "workspace",
+            instance: workspace.name.clone(),
+            full_text: format!(
+                "{}{} ({})",
+                workspace.indicator(),
+                workspace.name,
+                counts.get(&workspace.name).copied().unwrap_or(0)
+            )
+            .trim_start()
+            .to_string(),
+            urgent: workspace.urgent,
+        });
+    }
+
+    let n_urgent = workspaces.iter().filter(|ws| ws.urgent).count();
+    if n_urgent > 0 {
+        blocks.push(BarBlock {
+            name: "urgent",
+            instance: String::new(),
+            full_text: format!("{} urgent", n_urgent),
+            urgent: true,
+        });
+    }
+    blocks
+}
+
+fn plain_line(blocks: &[BarBlock]) -> String {
+    blocks
+        .iter()
+        .map(|block| block.full_text.as_str())
+        .collect::<Vec<&str>>()
+        .join(" | ")
+}
+
+/// Whether an event can change what the blocks show. Title and mark changes only matter for
+/// the focused window, everything else does.
+fn affects_blocks(event: &Value) -> bool {
+    match event["change"].as_str() {
+        Some("title" | "mark") => event["container"]["focused"].as_bool().unwrap_or(true),
+        _ => true,
+    }
+}
+
+/// Act on a click: focus what got clicked, or toggle the TUI popup with a right click
+fn handle_click(click: &Click, settings: &Settings) -> Result<String> {
+    match (click.button, click.name.as_str()) {
+        (3, _) => popup::toggle(settings),
+        (_, "focused") => i3_cmd(&[&format!(r#"[con_id="{}"]"#, click.instance), "focus"]),
+        (_, "workspace") => i3_cmd(&["workspace", &quoted(&click.instance)]),
+        _ => Ok(String::new()),
+    }
+}
+
+/// Handle the click events that i3bar writes to our stdin, one JSON object per line within
+/// an endless array.
+fn watch_clicks(settings: Settings) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(|line| line.ok()) {
+            let line = line.trim().trim_start_matches([',', '[']);
+            if let Ok(click) = serde_json::from_str::<Click>(line) {
+                if let Err(err) = handle_click(&click, &settings) {
+                    eprintln!("Unable to handle {:?}: {}", click, err);
+                }
+            }
+        }
+    });
+}
+
+/// Print the status of the tree every time it changes, until stdout gets closed.
+pub(crate) fn run(plain: bool) -> Result<()> {
+    let settings = Settings::load()?;
+    let events = events::subscribe::<Value>(&["window", "workspace", "output"])?;
+    let mut out = std::io::stdout().lock();
+
+    if !plain {
+        writeln!(out, r#"{{"version": 1, "click_events": true}}"#)?;
+        writeln!(out, "[")?;
+        watch_clicks(settings);
+    }
+
+    // Read once, restart the bar to follow a new order
+    let sort = WorkspaceSort::load();
+    loop {
+        // Straight from i3, the daemon may not have caught up with the events yet
+        let blocks = blocks(&Snapshot::collect()?, sort);
+        if plain {
+            writeln!(out, "{}", plain_line(&blocks))?;
+        } else {
+            writeln!(out, "{},", serde_json::to_string(&blocks)?)?;
+        }
+        out.flush()?;
+
+        // Events come in bursts, draw them all at once
+        loop {
+            let Ok(event) = events.recv() else {
+                return Err("Lost the i3 event subscription".into());
+            };
+            let pending: Vec<Value> = std::iter::once(event).chain(events.try_iter()).collect();
+            if pending.iter().any(affects_blocks) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::fixture;
+
+    #[test]
+    fn blocks_of_snapshot() {
+        let snapshot: Snapshot = serde_json::from_value(serde_json::json!({
+            "windows": [], "attached_windows": [], "floating_windows": [],
+            "ws_map": {}, "ws_map_names": {}, "ws_trees": {},
+            "workspaces": [
+                {"id": 2, "name": "2: web", "num": 2, "urgent": true},
+                {"id": 1, "name": "1", "num": 1, "focused": true, "visible": true},
+            ],
+            "focused": fixture::node(serde_json::json!({
+                "name": "vim", "id": 7, "window": 42, "focused": true, "class": "Alacritty",
+            })),
+        }))
+        .unwrap();
+
+        let blocks = blocks(&snapshot, WorkspaceSort::Number);
+        let texts: Vec<&str> = blocks.iter().map(|b| b.full_text.as_str()).collect();
+        assert_eq!(texts[1..], ["*1 (0)", "!2: web (0)", "1 urgent"]);
+        assert_eq!(blocks[0].instance, "7");
+        assert!(plain_line(&blocks).starts_with("[Alacritty      ] vim"));
+
+        let json = serde_json::to_value(&blocks[1]).unwrap();
+        assert_eq!(json.get("urgent"), None);
+
+        let title = |focused: bool| serde_json::json!({"change": "title", "container": {"focused": focused}});
+        assert!(affects_blocks(&title(true)));
+        assert!(!affects_blocks(&title(false)));
+        assert!(affects_blocks(&serde_json::json!({"change": "new"})));
+    }
+}
@@ -0,0 +1,216 @@
+//! Maintenance: find what a long session leaves behind, and get rid of it in one go.
+//!
+//! That is empty workspaces, windows without a title, windows whose process is gone (PID no
+//! longer in /proc) and windows on the workspaces of a disabled output.
+
+use crate::output::{list_outputs, Output};
+use crate::prelude::*;
+use crate::process::{hostname, window_machine, window_pid, KillMethod};
+use crate::shutils::{self, i3_cmd, quoted};
+use crate::window::{list_workspace_trees, list_workspaces, Window};
+use crate::workspace::Workspace;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Issue {
+    EmptyWorkspace,
+    Untitled,
+    /// The PID the window claims through `_NET_WM_PID`
+    DeadProcess(u32),
+    /// The name of the output
+    DisabledOutput(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Finding {
+    pub(crate) issue: Issue,
+    pub(crate) workspace: String,
+    /// None for empty workspaces
+    pub(crate) window: Option<Window>,
+}
+
+impl Finding {
+    /// e.g. `dead process 1234: [Alacritty] vim (on 3)`
+    pub(crate) fn finding_str(&self) -> String {
+        let issue = match &self.issue {
+            Issue::EmptyWorkspace => "empty workspace".to_string(),
+            Issue::Untitled => "untitled".to_string(),
+            Issue::DeadProcess(pid) => format!("dead process {}", pid),
+            Issue::DisabledOutput(output) => format!("disabled output {}", output),
+        };
+        match &self.window {
+            Some(window) => format!(
+                "{}: [{}] {} (on {})",
+                issue,
+                window.class_name().unwrap_or_default(),
+                window.name.clone().unwrap_or_default(),
+                self.workspace
+            ),
+            None => format!("{}: {}", issue, self.workspace),
+        }
+    }
+}
+
+/// What to do with the findings
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Fix {
+    /// Close the windows and leave the empty workspaces, which i3 then drops
+    Close,
+    /// Move the windows to the given workspace
+    Relocate(String),
+}
+
+/// The PID of a window whose process no longer exists. Only the windows of local clients are
+/// checked, since the PIDs of remote ones don't live in our /proc.
+pub(crate) fn dead_pid(window: &Window) -> Option<u32> {
+    let xid = window.window?;
+    let local = hostname();
+    if local.is_none() || window_machine(xid) != local {
+        return None;
+    }
+    window_pid(xid).filter(|pid| !std::path::Path::new(&format!("/proc/{}", pid)).exists())
+}
+
+/// Look for problems in the trees of the workspaces, `dead_pid` telling whether the process of
+/// a window is gone.
+pub(crate) fn scan(
+    workspaces: &[Workspace],
+    trees: &HashMap<String, Window>,
+    outputs: &[Output],
+    dead_pid: impl Fn(&Window) -> Option<u32>,
+) -> Vec<Finding> {
+    let disabled: HashSet<&str> = outputs
+        .iter()
+        .filter(|output| !output.active)
+        .map(|output| output.name.as_str())
+        .collect();
+
+    let mut findings = vec![];
+    for workspace in workspaces {
+        let windows: Vec<Window> = trees
+            .get(&workspace.name)
+            .map(|tree| tree.layout_leaves())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect();
+        let finding = |issue: Issue, window: Option<&Window>| Finding {
+            issue,
+            workspace: workspace.name.clone(),
+            window: window.cloned(),
+        };
+
+        if windows.is_empty() {
+            findings.push(finding(Issue::EmptyWorkspace, None));
+        }
+        for window in &windows {
+            if window.name.as_deref().unwrap_or_default().trim().is_empty() {
+                findings.push(finding(Issue::Untitled, Some(window)));
+            }
+            if let Some(pid) = dead_pid(window) {
+                findings.push(finding(Issue::DeadProcess(pid), Some(window)));
+            }
+            if disabled.contains(workspace.output.as_str()) {
+                let issue = Issue::DisabledOutput(workspace.output.clone());
+                findings.push(finding(issue, Some(window)));
+            }
+        }
+    }
+    findings
+}
+
+/// Scan the current tree of i3
+pub(crate) fn scan_current() -> Result<Vec<Finding>> {
+    Ok(scan(
+        &list_workspaces(),
+        &list_workspace_trees()?,
+        &list_outputs()?,
+        dead_pid,
+    ))
+}
+
+/// Apply `fix` to the findings, once per window or workspace, then go back to the workspace
+/// that was focused. Returns how many windows and workspaces were handled.
+pub(crate) fn apply(findings: &[Finding], fix: &Fix, focused: &str) -> Result<usize> {
+    let mut windows = HashSet::new();
+    let mut handled = 0;
+    let mut left_focused = false;
+    for finding in findings {
+        match (&finding.window, fix) {
+            (Some(window), _) if !windows.insert(window.id) => continue,
+            (Some(window), Fix::Close) => {
+                window.kill_with(KillMethod::Close)?;
+            }
+            (Some(window), Fix::Relocate(workspace)) => {
+                shutils::move_window_to_workspace(window.id, workspace)?;
+            }
+            (None, Fix::Close) => {
+                // i3 drops an empty workspace as soon as it isn't shown anymore
+                i3_cmd(&[&format!(
+                    "workspace {}; workspace next_on_output",
+                    quoted(&finding.workspace)
+                )])?;
+                left_focused |= finding.workspace == focused;
+            }
+            (None, Fix::Relocate(_)) => continue,
+        }
+        handled += 1;
+    }
+
+    if !left_focused && !focused.is_empty() {
+        i3_cmd(&["workspace", &quoted(focused)])?;
+    }
+    Ok(handled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::fixture;
+    use serde_json::json;
+
+    #[test]
+    fn scan_workspaces() {
+        let workspaces: Vec<Workspace> = serde_json::from_value(json!([
+            {"id": 1, "name": "1", "output": "eDP-1"},
+            {"id": 2, "name": "2", "output": "HDMI-1"},
+            {"id": 3, "name": "3", "output": "eDP-1"},
+        ]))
+        .unwrap();
+        let outputs: Vec<Output> = serde_json::from_value(json!([
+            {"name": "eDP-1", "active": true, "primary": true,
+             "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080}},
+            {"name": "HDMI-1", "active": false, "primary": false,
+             "rect": {"x": 0, "y": 0, "width": 0, "height": 0}},
+        ]))
+        .unwrap();
+        let window = |id: u64, name: &str| fixture::window(id, None, name);
+        let tree = |nodes: Vec<Window>| fixture::workspace("splith", nodes);
+        let trees = HashMap::from([
+            (
+                "1".to_string(),
+                tree(vec![window(10, "vim"), window(11, " ")]),
+            ),
+            ("2".to_string(), tree(vec![window(20, "mpv")])),
+            ("3".to_string(), tree(vec![])),
+        ]);
+
+        let findings = scan(&workspaces, &trees, &outputs, |w| {
+            (w.id == 10).then_some(42)
+        });
+        let summary: Vec<(Issue, Option<u64>)> = findings
+            .iter()
+            .map(|f| (f.issue.clone(), f.window.as_ref().map(|w| w.id)))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Issue::DeadProcess(42), Some(10)),
+                (Issue::Untitled, Some(11)),
+                (Issue::DisabledOutput("HDMI-1".to_string()), Some(20)),
+                (Issue::EmptyWorkspace, None),
+            ]
+        );
+        assert_eq!(findings[3].finding_str(), "empty workspace: 3");
+    }
+}
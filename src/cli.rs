@@ -0,0 +1,297 @@
+//! Subcommands that can be run without opening the TUI.
+//!
+//! They are served by the daemon when one is running and executed in-process otherwise.
+
+use crate::bar;
+use crate::cleanup::{self, Fix};
+use crate::config::Settings;
+use crate::daemon;
+use crate::export::{export_current, ExportFormat};
+use crate::history::HistoryEntry;
+use crate::popup;
+use crate::prelude::*;
+use crate::screenshot::{self, Crop, ScreenshotOptions};
+use crate::swap;
+use crate::template::{apply_template, load_templates};
+use crate::window::{list_workspaces, ordered_trees, Snapshot, Window};
+use serde_json::{json, Value};
+
+const USAGE: &str = "Usage: i3-mgr [COMMAND]
+
+Without a command, open the TUI.
+
+Commands:
+    daemon                      Keep the i3 tree in memory and serve it over a Unix socket
+    list                        List the windows of every workspace
+    focus <CON_ID>              Focus a window
+    move <CON_ID> <WORKSPACE>   Move a window to a workspace
+    consolidate <WORKSPACE>     Move all windows to a single workspace
+    screenshot <QUERY> -o FILE  Save a window (con id, or part of its class or title) as PNG,
+                                JPEG or WebP; add --scale 0.5 or --crop WxH+X+Y to transform it
+    screenshot --workspace <WS> -o DIR [--format png|jpg|webp]
+                                Save every window of a workspace, one file per window
+    swap ws <A> <B>             Exchange the windows of two workspaces
+    swap win <CON_ID> <CON_ID>  Exchange two windows, even on different workspaces
+    cleanup [close|move <WS>] [--yes]
+                                List the empty workspaces and left-behind windows, then close
+                                them or move them to a workspace once confirmed with --yes
+    history [list|prev|next]    Show the focus history or focus the previous/next window in it
+    template list               List the workspace templates
+    template apply <NAME>       Create the workspaces of a template and launch its applications
+    export <FORMAT> [-o FILE]   Print (or write) the tree as json, dot or md
+    bar [--plain]               Status output for i3bar, or one line per update with --plain
+    popup                       Show or hide the TUI in a scratchpad terminal, opening it if needed
+    help                        Print this message";
+
+fn usage_error(message: &str) -> Box<dyn std::error::Error> {
+    format!("{}\n\n{}", message, USAGE).into()
+}
+
+/// Retrieve the `index`th positional argument
+fn arg<'a>(args: &'a [String], index: usize, name: &str) -> Result<&'a str> {
+    args.get(index)
+        .map(|arg| arg.as_str())
+        .ok_or_else(|| usage_error(&format!("Missing argument <{}>", name)))
+}
+
+fn con_id_arg(args: &[String], index: usize) -> Result<u64> {
+    Ok(arg(args, index, "CON_ID")?.parse::<u64>()?)
+}
+
+/// Run the subcommand described by `args` (without the program name).
+pub(crate) fn run(args: &[String]) -> Result<()> {
+    let rest = &args[1..];
+    match args[0].as_str() {
+        "daemon" => daemon::run(),
+        "list" => list_cmd(),
+        "focus" => {
+            let result = daemon::call("focus", json!({ "id": con_id_arg(rest, 0)? }))?;
+            println!("{}", result);
+            Ok(())
+        }
+        "move" => {
+            let params = json!({
+                "id": con_id_arg(rest, 0)?,
+                "workspace": arg(rest, 1, "WORKSPACE")?,
+            });
+            println!("{}", daemon::call("move", params)?);
+            Ok(())
+        }
+        "consolidate" => {
+            let params = json!({ "workspace": arg(rest, 0, "WORKSPACE")? });
+            daemon::call("consolidate", params)?;
+            Ok(())
+        }
+        "screenshot" => screenshot_cmd(rest),
+        "swap" => swap_cmd(rest),
+        "cleanup" => cleanup_cmd(rest),
+        "history" => history_cmd(rest),
+        "template" => template_cmd(rest),
+        "export" => export_cmd(rest),
+        "bar" => match rest.first().map(|arg| arg.as_str()) {
+            None => bar::run(false),
+            Some("--plain") => bar::run(true),
+            Some(other) => Err(usage_error(&format!("Unexpected argument '{}'", other))),
+        },
+        "popup" => {
+            popup::toggle(&Settings::load()?)?;
+            Ok(())
+        }
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(usage_error(&format!("Unknown command '{}'", other))),
+    }
+}
+
+fn list_cmd() -> Result<()> {
+    let workspaces: Vec<(String, Vec<Window>)> =
+        serde_json::from_value(daemon::call("list", Value::Null)?)?;
+
+    for (workspace, windows) in workspaces {
+        println!("{}", workspace);
+        for window in windows {
+            println!("  {:>10} {}", window.id, window.name_str());
+        }
+    }
+    Ok(())
+}
+
+fn history_cmd(args: &[String]) -> Result<()> {
+    let action = args.first().map(|arg| arg.as_str()).unwrap_or("list");
+    match action {
+        "list" => {
+            let entries: Vec<HistoryEntry> =
+                serde_json::from_value(daemon::call("history", json!({ "action": "list" }))?)?;
+            for entry in entries {
+                println!("{:>10} {}", entry.id, entry.name_str());
+            }
+        }
+        "prev" | "next" => {
+            let entry: Option<HistoryEntry> =
+                serde_json::from_value(daemon::call("history", json!({ "action": action }))?)?;
+            if let Some(entry) = entry {
+                println!("{}", entry.name_str());
+            }
+        }
+        other => return Err(usage_error(&format!("Unknown history action '{}'", other))),
+    }
+    Ok(())
+}
+
+fn export_cmd(args: &[String]) -> Result<()> {
+    let name = arg(args, 0, "FORMAT")?;
+    let format = ExportFormat::parse(name)
+        .ok_or_else(|| usage_error(&format!("Unknown export format '{}'", name)))?;
+    let exported = export_current(format)?;
+
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("-o") => std::fs::write(arg(args, 2, "FILE")?, exported)?,
+        Some(other) => return Err(usage_error(&format!("Unexpected argument '{}'", other))),
+        None => print!("{}", exported),
+    }
+    Ok(())
+}
+
+fn screenshot_cmd(args: &[String]) -> Result<()> {
+    let mut query = None;
+    let mut flags: HashMap<&str, &str> = HashMap::new();
+    let mut index = 0;
+    while index < args.len() {
+        match args[index].as_str() {
+            flag @ ("-o" | "--scale" | "--crop" | "--workspace" | "--format") => {
+                flags.insert(flag, arg(args, index + 1, flag)?);
+                index += 1;
+            }
+            other if query.is_none() && !other.starts_with('-') => query = Some(other),
+            other => return Err(usage_error(&format!("Unexpected argument '{}'", other))),
+        }
+        index += 1;
+    }
+
+    let options = ScreenshotOptions {
+        crop: match flags.get("--crop") {
+            Some(crop) => Some(
+                Crop::parse(crop)
+                    .ok_or_else(|| usage_error(&format!("Invalid crop '{}'", crop)))?,
+            ),
+            None => None,
+        },
+        scale: match flags.get("--scale") {
+            Some(scale) => Some(screenshot::parse_scale(scale).ok_or_else(|| {
+                usage_error(&format!("Invalid scale '{}', expected (0, 16]", scale))
+            })?),
+            None => None,
+        },
+    };
+    let output = flags
+        .get("-o")
+        .ok_or_else(|| usage_error("Missing argument -o"))?;
+    match (flags.get("--format"), flags.contains_key("--workspace")) {
+        (Some(format), true) => screenshot::check_extension(format)?,
+        (Some(_), false) => {
+            return Err(usage_error(
+                "--format only goes with --workspace, the extension of -o picks it otherwise",
+            ))
+        }
+        (None, _) => (),
+    }
+    let snapshot = daemon::snapshot().or_else(|_| Snapshot::collect())?;
+    let trees = ordered_trees(&snapshot.workspaces, &snapshot.ws_trees);
+    let windows_of = |tree: &Window| -> Vec<Window> {
+        tree.layout_leaves()
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect()
+    };
+
+    if let Some(workspace) = flags.get("--workspace") {
+        let tree = trees
+            .iter()
+            .find(|(name, _)| name == workspace)
+            .ok_or_else(|| format!("No workspace named {}", workspace))?;
+        let extension = flags.get("--format").copied().unwrap_or("png");
+        let (written, failed) = screenshot::screenshot_windows(
+            &windows_of(tree.1),
+            std::path::Path::new(output),
+            extension,
+            &options,
+        )?;
+        for path in written {
+            println!("{}", path.display());
+        }
+        for failure in failed {
+            eprintln!("{}", failure);
+        }
+        return Ok(());
+    }
+
+    let query = query.ok_or_else(|| usage_error("Missing argument <QUERY>"))?;
+    let windows: Vec<Window> = trees
+        .iter()
+        .flat_map(|(_, tree)| windows_of(tree))
+        .collect();
+    let window = screenshot::find_window(&windows, query)
+        .ok_or_else(|| format!("No window matches '{}'", query))?;
+    screenshot::screenshot_window(window, std::path::Path::new(output), &options)
+}
+
+fn swap_cmd(args: &[String]) -> Result<()> {
+    match arg(args, 0, "ws|win")? {
+        "ws" => swap::swap_workspaces(arg(args, 1, "A")?, arg(args, 2, "B")?),
+        "win" => {
+            println!(
+                "{}",
+                swap::swap_windows(con_id_arg(args, 1)?, con_id_arg(args, 2)?)?
+            );
+            Ok(())
+        }
+        other => Err(usage_error(&format!("Unknown swap target '{}'", other))),
+    }
+}
+
+fn cleanup_cmd(args: &[String]) -> Result<()> {
+    let confirmed = args.iter().any(|arg| arg == "--yes");
+    let args: Vec<String> = args.iter().filter(|arg| *arg != "--yes").cloned().collect();
+    let fix = match args.first().map(|arg| arg.as_str()) {
+        None | Some("list") => None,
+        Some("close") => Some(Fix::Close),
+        Some("move") => Some(Fix::Relocate(arg(&args, 1, "WORKSPACE")?.to_string())),
+        Some(other) => return Err(usage_error(&format!("Unknown cleanup action '{}'", other))),
+    };
+
+    let findings = cleanup::scan_current()?;
+    for finding in &findings {
+        println!("{}", finding.finding_str());
+    }
+    match fix {
+        Some(_) if findings.is_empty() => println!("Nothing to clean up"),
+        Some(_) if !confirmed => println!("Run again with --yes to clean these up"),
+        Some(fix) => {
+            let focused = list_workspaces()
+                .into_iter()
+                .find(|ws| ws.focused)
+                .map(|ws| ws.name)
+                .unwrap_or_default();
+            let handled = cleanup::apply(&findings, &fix, &focused)?;
+            println!("Cleaned up {} windows and workspaces", handled);
+        }
+        None => (),
+    }
+    Ok(())
+}
+
+fn template_cmd(args: &[String]) -> Result<()> {
+    match args.first().map(|arg| arg.as_str()).unwrap_or("list") {
+        "list" => {
+            for (name, workspaces) in load_templates()? {
+                let names: Vec<&str> = workspaces.iter().map(|w| w.workspace.as_str()).collect();
+                println!("{} ({})", name, names.join(", "));
+            }
+            Ok(())
+        }
+        "apply" => apply_template(arg(args, 1, "NAME")?),
+        other => Err(usage_error(&format!("Unknown template action '{}'", other))),
+    }
+}
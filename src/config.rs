@@ -0,0 +1,109 @@
+//! User configuration files, living under `$XDG_CONFIG_HOME/i3-mgr` (or `~/.config/i3-mgr`).
+
+use crate::prelude::*;
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+
+pub(crate) const SETTINGS_FILE: &str = "config.json";
+
+/// What the TUI does once an action took the focus elsewhere
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PostAction {
+    /// Keep the TUI open where it is
+    Stay,
+    /// Send the TUI back to the scratchpad when it runs in the popup
+    Hide,
+    /// Close the TUI
+    Quit,
+}
+
+/// The post-action of every action that leaves the TUI
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub(crate) struct AfterActions {
+    /// Going to a workspace or window
+    pub(crate) goto: PostAction,
+    /// Applying a template
+    pub(crate) template: PostAction,
+    /// Creating a workspace
+    pub(crate) new_workspace: PostAction,
+    /// Cancelling with Esc, back to the focus the TUI was opened on
+    pub(crate) cancel: PostAction,
+}
+
+impl Default for AfterActions {
+    fn default() -> Self {
+        AfterActions {
+            goto: PostAction::Hide,
+            template: PostAction::Hide,
+            new_workspace: PostAction::Hide,
+            cancel: PostAction::Hide,
+        }
+    }
+}
+
+/// General settings, read from `config.json`
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub(crate) struct Settings {
+    /// Append the log of the TUI to `$XDG_STATE_HOME/i3-mgr/i3-mgr.log`
+    pub(crate) log_file: bool,
+    /// Terminal emulator the TUI gets opened in, followed by `-e i3-mgr`
+    pub(crate) terminal: String,
+    /// Class of a terminal to adopt as the popup, for terminals started outside `i3-mgr popup`
+    pub(crate) popup_class: String,
+    /// Size of the popup, in percents of the output
+    pub(crate) popup_width: u16,
+    pub(crate) popup_height: u16,
+    pub(crate) after: AfterActions,
+    /// Percentage points the layout mode grows or shrinks a window by
+    pub(crate) resize_step: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            log_file: false,
+            terminal: "i3-sensible-terminal".to_string(),
+            popup_class: String::new(),
+            popup_width: 80,
+            popup_height: 70,
+            after: AfterActions::default(),
+            resize_step: 10,
+        }
+    }
+}
+
+impl Settings {
+    pub(crate) fn load() -> Result<Self> {
+        load(SETTINGS_FILE)
+    }
+}
+
+/// Directory holding all of our configuration files
+pub(crate) fn config_dir() -> PathBuf {
+    let base = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".config"),
+    };
+    base.join("i3-mgr")
+}
+
+/// Full path of a configuration file
+pub(crate) fn config_file(name: &str) -> PathBuf {
+    config_dir().join(name)
+}
+
+/// Load a configuration file. A missing file yields the default value, whereas an invalid one
+/// is reported so that typos don't go unnoticed.
+pub(crate) fn load<T: DeserializeOwned + Default>(name: &str) -> Result<T> {
+    let path = config_file(name);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let content = std::fs::read_to_string(&path)?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("Invalid configuration {}: {}", path.display(), err).into())
+}
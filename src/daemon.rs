@@ -0,0 +1,381 @@
+//! Long running daemon keeping the i3 tree in memory and serving it over a Unix socket.
+//!
+//! Clients write one JSON-RPC 2.0 request per line and get one response per line back, e.g.
+//!
+//! ```text
+//! {"jsonrpc": "2.0", "id": 1, "method": "focus", "params": {"id": 94823749283}}
+//! {"jsonrpc": "2.0", "id": 1, "result": [{"success": true}]}
+//! ```
+//!
+//! Every method can also be executed in-process with [`call`], which is what the CLI does when
+//! no daemon is running.
+
+use crate::events;
+use crate::history::History;
+use crate::prelude::*;
+use crate::rules::RulesEngine;
+use crate::shutils::{self, i3_cmd};
+use crate::window::{consolidate_workspaces, Snapshot, Window};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// Where the daemon listens for requests
+pub(crate) fn socket_path() -> PathBuf {
+    match std::env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("i3-mgr.sock"),
+        _ => std::env::temp_dir().join(format!(
+            "i3-mgr-{}.sock",
+            std::env::var("USER").unwrap_or_default()
+        )),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for RpcError {
+    fn from(err: Box<dyn std::error::Error>) -> Self {
+        RpcError::new(SERVER_ERROR, err)
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(err: serde_json::Error) -> Self {
+        RpcError::new(SERVER_ERROR, err)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Response {
+    jsonrpc: String,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    fn new(id: Value, outcome: std::result::Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Response {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// What the daemon keeps in memory between requests
+struct DaemonState {
+    snapshot: Snapshot,
+    history: History,
+    rules: RulesEngine,
+}
+
+impl DaemonState {
+    /// Lock the state, even when a thread panicked while holding it: the worst it can have left
+    /// behind is a stale snapshot, which the next event replaces anyway.
+    fn lock(state: &Mutex<DaemonState>) -> MutexGuard<'_, DaemonState> {
+        state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn param_str<'a>(params: &'a Value, name: &str) -> std::result::Result<&'a str, RpcError> {
+    params[name].as_str().ok_or(RpcError::new(
+        INVALID_PARAMS,
+        format!("Missing string parameter '{}'", name),
+    ))
+}
+
+fn param_u64(params: &Value, name: &str) -> std::result::Result<u64, RpcError> {
+    params[name].as_u64().ok_or(RpcError::new(
+        INVALID_PARAMS,
+        format!("Missing integer parameter '{}'", name),
+    ))
+}
+
+/// Turn an i3 reply into JSON, keeping it as a plain string if it isn't valid JSON.
+fn i3_reply(reply: String) -> Value {
+    serde_json::from_str(&reply).unwrap_or(Value::String(reply))
+}
+
+/// Execute a single method against a snapshot of the tree.
+///
+/// Returns the result along with whether the tree was modified and should be collected again.
+fn execute(
+    method: &str,
+    params: &Value,
+    snapshot: &Snapshot,
+    history: &mut History,
+    rules: &mut RulesEngine,
+) -> std::result::Result<(Value, bool), RpcError> {
+    match method {
+        "snapshot" | "refresh" => Ok((serde_json::to_value(snapshot)?, false)),
+        "list" => Ok((serde_json::to_value(snapshot.workspace_windows())?, false)),
+        "focus" => {
+            let id = param_u64(params, "id")?;
+            let reply = i3_cmd(&[&format!(r#"[con_id="{}"]"#, id), "focus"])?;
+            Ok((i3_reply(reply), true))
+        }
+        "move" => {
+            let id = param_u64(params, "id")?;
+            let workspace = param_str(params, "workspace")?;
+            let reply = shutils::move_window_to_workspace(id, workspace)?;
+            Ok((i3_reply(reply), true))
+        }
+        "consolidate" => {
+            let workspace = param_str(params, "workspace")?;
+            consolidate_workspaces(&snapshot.ws_map, workspace)?;
+            Ok((Value::Null, true))
+        }
+        "history" => match params["action"].as_str().unwrap_or("list") {
+            "list" => Ok((serde_json::to_value(history.entries())?, false)),
+            action @ ("prev" | "next") => {
+                if let Some(focused) = &snapshot.focused {
+                    history.record(focused);
+                }
+                let alive: Vec<u64> = snapshot.windows.iter().map(|w| w.id).collect();
+                let entry = history.step(action == "prev", &alive);
+                if let Some(entry) = &entry {
+                    entry.focus()?;
+                }
+                history.save()?;
+                Ok((serde_json::to_value(entry)?, true))
+            }
+            other => Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Unknown history action '{}'", other),
+            )),
+        },
+        "rules" => {
+            if params["action"].as_str() == Some("reload") {
+                rules.reload();
+            }
+            Ok((serde_json::to_value(rules.snapshot())?, false))
+        }
+        other => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method '{}'", other),
+        )),
+    }
+}
+
+/// Whether a daemon is listening on our socket
+pub(crate) fn is_running() -> bool {
+    UnixStream::connect(socket_path()).is_ok()
+}
+
+/// Send a request to the running daemon.
+pub(crate) fn request(method: &str, params: Value) -> Result<Value> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+    writeln!(stream, "{}", request)?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response: Response = serde_json::from_str(&line)?;
+
+    match response.error {
+        Some(error) => Err(error.message.into()),
+        None => Ok(response.result.unwrap_or(Value::Null)),
+    }
+}
+
+/// Run a method through the daemon when it is running, or in-process otherwise.
+pub(crate) fn call(method: &str, params: Value) -> Result<Value> {
+    if is_running() {
+        return request(method, params);
+    }
+
+    let mut history = History::load();
+    let mut rules = RulesEngine::load();
+    execute(
+        method,
+        &params,
+        &Snapshot::collect()?,
+        &mut history,
+        &mut rules,
+    )
+    .map(|(result, _)| result)
+    .map_err(|error| error.message.into())
+}
+
+/// Retrieve the daemon's copy of the tree
+pub(crate) fn snapshot() -> Result<Snapshot> {
+    Ok(serde_json::from_value(request("snapshot", Value::Null)?)?)
+}
+
+/// Have the daemon collect the tree right away, and retrieve it
+pub(crate) fn refresh() -> Result<Snapshot> {
+    Ok(serde_json::from_value(request("refresh", Value::Null)?)?)
+}
+
+/// Record the focus events, apply the rules to new windows and collect the tree again whenever
+/// i3 notifies us of a change.
+fn watch_events(state: Arc<Mutex<DaemonState>>) -> Result<()> {
+    let events = events::subscribe::<Value>(&["window", "workspace", "output"])?;
+
+    std::thread::spawn(move || {
+        while let Ok(event) = events.recv() {
+            // Events come in bursts (e.g. new + focus + title), collect the tree only once
+            let pending: Vec<Value> = std::iter::once(event).chain(events.try_iter()).collect();
+
+            {
+                let mut state = DaemonState::lock(&state);
+                for event in pending.iter().filter(|e| e["change"] == "new") {
+                    if let Ok(window) = serde_json::from_value::<Window>(event["container"].clone())
+                    {
+                        state.rules.handle_new_window(&window);
+                    }
+                }
+            }
+
+            // Clients keep being served the previous snapshot while this one is collected
+            let snapshot = Snapshot::collect();
+            let mut state = DaemonState::lock(&state);
+            let mut history_changed = false;
+            for event in pending.iter().filter(|e| e["change"] == "focus") {
+                if let Ok(window) = serde_json::from_value::<Window>(event["container"].clone()) {
+                    history_changed |= state.history.record(&window);
+                }
+            }
+            if history_changed {
+                let _ = state.history.save();
+            }
+            match snapshot {
+                Ok(snapshot) => state.snapshot = snapshot,
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        eprintln!("Lost the i3 event subscription, the tree won't be updated anymore");
+    });
+
+    Ok(())
+}
+
+/// Answer the requests of a single client until it hangs up.
+fn serve(stream: UnixStream, state: &Mutex<DaemonState>) -> Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let outcome = if request.method == "refresh" {
+                    // For clients that just changed the tree and can't wait for the events
+                    Snapshot::collect()
+                        .map_err(RpcError::from)
+                        .and_then(|snapshot| {
+                            let value = serde_json::to_value(&snapshot)?;
+                            DaemonState::lock(state).snapshot = snapshot;
+                            Ok((value, false))
+                        })
+                } else {
+                    let mut state = DaemonState::lock(state);
+                    let DaemonState {
+                        snapshot,
+                        history,
+                        rules,
+                    } = &mut *state;
+                    execute(&request.method, &request.params, snapshot, history, rules)
+                };
+                let outcome = outcome.map(|(result, modified)| {
+                    if modified {
+                        match Snapshot::collect() {
+                            Ok(snapshot) => DaemonState::lock(state).snapshot = snapshot,
+                            Err(err) => eprintln!("{}", err),
+                        }
+                    }
+                    result
+                });
+                Response::new(request.id, outcome)
+            }
+            Err(err) => Response::new(Value::Null, Err(RpcError::new(PARSE_ERROR, err))),
+        };
+
+        writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+    }
+
+    Ok(())
+}
+
+/// Run the daemon until killed.
+pub(crate) fn run() -> Result<()> {
+    let path = socket_path();
+    if is_running() {
+        return Err(format!("A daemon is already listening on {}", path.display()).into());
+    }
+    // A previous daemon may have left its socket behind
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+
+    let snapshot = Snapshot::collect()?;
+    let mut history = History::load();
+    if let Some(focused) = &snapshot.focused {
+        history.record(focused);
+    }
+    let state = Arc::new(Mutex::new(DaemonState {
+        snapshot,
+        history,
+        rules: RulesEngine::load(),
+    }));
+
+    watch_events(Arc::clone(&state))?;
+    println!("Listening on {}", path.display());
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let state = Arc::clone(&state);
+        std::thread::spawn(move || {
+            if let Err(err) = serve(stream, &state) {
+                eprintln!("Client error: {}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Subscribe to the i3 event stream.
+//!
+//! We keep in line with the rest of the crate and let `i3-msg` do the IPC: in monitor mode it
+//! prints one JSON payload per line, which a background thread parses and forwards.
+
+use crate::prelude::*;
+use crate::shutils::cmd;
+use crate::window::Window;
+use serde::de::DeserializeOwned;
+use std::io::{BufRead, BufReader};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Payload of an i3 `window` event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct WindowEvent {
+    /// One of new, close, focus, title, fullscreen_mode, move, floating, urgent or mark
+    pub(crate) change: String,
+    pub(crate) container: Window,
+}
+
+/// Subscribe to the given i3 event types (e.g. `["window"]`), parsing every payload as `T`.
+///
+/// Payloads that cannot be parsed as `T` (like the initial subscription reply) are skipped.
+pub(crate) fn subscribe<T>(event_types: &[&str]) -> Result<Receiver<T>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let mut child = cmd(&[
+        "i3-msg",
+        "-t",
+        "subscribe",
+        "-m",
+        &serde_json::to_string(event_types)?,
+    ])
+    .stdout(Stdio::piped())
+    .spawn()?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or("Unable to capture i3-msg output")?;
+    let (sender, receiver) = channel();
+
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+            if let Ok(event) = serde_json::from_str::<T>(&line) {
+                if sender.send(event).is_err() {
+                    // Nobody is listening anymore
+                    break;
+                }
+            }
+        }
+        let _ = child.kill();
+    });
+
+    Ok(receiver)
+}
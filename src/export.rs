@@ -0,0 +1,194 @@
+//! Export the i3 tree, workspace by workspace, to share layouts in bug reports or debug them.
+//!
+//! All the formats walk the `nodes`/`floating_nodes` hierarchy of each workspace so that the
+//! split containers, and not only the windows, show up.
+
+use crate::daemon;
+use crate::prelude::*;
+use crate::state;
+use crate::window::{ordered_trees, Snapshot, Window};
+use serde_json::json;
+use std::path::PathBuf;
+
+/// Directory under the state directory where the TUI writes its exports
+const EXPORT_DIR: &str = "export";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum ExportFormat {
+    Json,
+    Dot,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub(crate) const ALL: [Self; 3] = [
+        ExportFormat::Json,
+        ExportFormat::Dot,
+        ExportFormat::Markdown,
+    ];
+
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(ExportFormat::Json),
+            "dot" => Some(ExportFormat::Dot),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Dot => "dot",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    /// Render the trees of the given workspaces, in order
+    pub(crate) fn render(&self, trees: &[(&str, &Window)]) -> Result<String> {
+        match self {
+            ExportFormat::Json => to_json(trees),
+            ExportFormat::Dot => Ok(to_dot(trees)),
+            ExportFormat::Markdown => Ok(to_markdown(trees)),
+        }
+    }
+}
+
+/// Children of a container, flagging the floating ones
+fn children(node: &Window) -> impl Iterator<Item = (&Window, bool)> {
+    let tiled = node.nodes.iter().flatten().map(|child| (child, false));
+    let floating = node
+        .floating_nodes
+        .iter()
+        .flatten()
+        .map(|child| (child, true));
+    tiled.chain(floating)
+}
+
+fn to_json(trees: &[(&str, &Window)]) -> Result<String> {
+    let workspaces: Vec<serde_json::Value> = trees
+        .iter()
+        .map(|(workspace, tree)| json!({ "workspace": workspace, "tree": tree }))
+        .collect();
+    Ok(serde_json::to_string_pretty(&workspaces)?)
+}
+
+/// Escape a string for a quoted DOT identifier
+fn dot_escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn dot_node(node: &Window, lines: &mut Vec<String>) {
+    let shape = if node.window.is_some() {
+        "ellipse"
+    } else {
+        "box"
+    };
+    lines.push(format!(
+        "    n{} [label=\"{}\\n#{}\", shape={}];",
+        node.id,
+        dot_escape(&node.describe()),
+        node.id,
+        shape
+    ));
+
+    for (child, floating) in children(node) {
+        let style = if floating { " [style=dashed]" } else { "" };
+        lines.push(format!("    n{} -> n{}{};", node.id, child.id, style));
+        dot_node(child, lines);
+    }
+}
+
+fn to_dot(trees: &[(&str, &Window)]) -> String {
+    let mut lines = vec!["digraph i3 {".to_string(), "    rankdir=LR;".to_string()];
+    for (_, tree) in trees {
+        dot_node(tree, &mut lines);
+    }
+    lines.push("}".to_string());
+    lines.join("\n") + "\n"
+}
+
+fn markdown_node(node: &Window, depth: usize, floating: bool, lines: &mut Vec<String>) {
+    let description = match node.window {
+        Some(_) => node.describe(),
+        None => format!("**{}**", node.describe()),
+    };
+    lines.push(format!(
+        "{}- {}{} (#{})",
+        "  ".repeat(depth),
+        description,
+        if floating { " _floating_" } else { "" },
+        node.id
+    ));
+
+    for (child, floating) in children(node) {
+        markdown_node(child, depth + 1, floating, lines);
+    }
+}
+
+fn to_markdown(trees: &[(&str, &Window)]) -> String {
+    let mut lines = vec![];
+    for (workspace, tree) in trees {
+        lines.push(format!("## Workspace {}", workspace));
+        lines.push(String::new());
+        for (child, floating) in children(tree) {
+            markdown_node(child, 0, floating, &mut lines);
+        }
+        lines.push(String::new());
+    }
+    lines.join("\n")
+}
+
+/// Export the current tree, from the daemon when it is running
+pub(crate) fn export_current(format: ExportFormat) -> Result<String> {
+    let snapshot = daemon::snapshot().or_else(|_| Snapshot::collect())?;
+    format.render(&ordered_trees(&snapshot.workspaces, &snapshot.ws_trees))
+}
+
+/// Write the trees in every format to the export directory, returning that directory
+pub(crate) fn export_all(trees: &[(&str, &Window)]) -> Result<PathBuf> {
+    let dir = state::state_file(EXPORT_DIR);
+    std::fs::create_dir_all(&dir)?;
+    for format in ExportFormat::ALL {
+        let path = dir.join(format!("tree.{}", format.extension()));
+        std::fs::write(path, format.render(trees)?)?;
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::fixture;
+
+    #[test]
+    fn export_nested_containers() {
+        let vim = fixture::node(json!({
+            "name": "vim \"notes\"", "id": 3, "window": 42, "focused": true,
+            "window_properties": {"class": "Alacritty"},
+        }));
+        let tabbed = fixture::node(json!({"id": 2, "layout": "tabbed", "nodes": [vim]}));
+        let calculator = fixture::node(json!({
+            "name": "Calculator", "id": 4, "window": 43, "floating": "user_on", "class": "Gcalc",
+        }));
+        let mut tree = fixture::workspace("splith", vec![tabbed]);
+        tree.floating_nodes = Some(vec![calculator]);
+        let trees = [("1", &tree)];
+
+        assert_eq!(
+            to_markdown(&trees),
+            "## Workspace 1\n\n\
+             - **con tabbed** (#2)\n  \
+               - Alacritty: vim \"notes\" (#3)\n\
+             - Gcalc: Calculator _floating_ (#4)\n"
+        );
+
+        let dot = to_dot(&trees);
+        assert!(dot.contains(r#"n3 [label="Alacritty: vim \"notes\"\n#3", shape=ellipse];"#));
+        assert!(dot.contains("n2 -> n3;"));
+        assert!(dot.contains("n1 -> n4 [style=dashed];"));
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&trees).unwrap()).unwrap();
+        assert_eq!(json[0]["tree"]["nodes"][0]["nodes"][0]["window"], 42);
+    }
+}
@@ -0,0 +1,148 @@
+//! Windows of every workspace grouped by application class, for the class view of the
+//! Attached pane.
+
+use crate::prelude::*;
+use crate::process::KillMethod;
+use crate::shutils;
+use crate::window::Window;
+use std::collections::{BTreeMap, HashSet};
+
+/// Label of the group of windows that don't set a class
+const NO_CLASS: &str = "(no class)";
+
+#[derive(Debug, Clone)]
+pub(crate) struct GroupedWindow {
+    pub(crate) workspace: String,
+    pub(crate) window: Window,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ClassGroup {
+    pub(crate) class: String,
+    pub(crate) windows: Vec<GroupedWindow>,
+}
+
+/// A line of the class view: either the header of a group or one of its windows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum GroupRow {
+    Group(usize),
+    Window(usize, usize),
+}
+
+/// Group the windows of the given workspace trees by class, the largest groups first.
+pub(crate) fn group_by_class<'a>(
+    trees: impl Iterator<Item = (&'a str, &'a Window)>,
+) -> Vec<ClassGroup> {
+    let mut groups: BTreeMap<String, Vec<GroupedWindow>> = BTreeMap::new();
+    for (workspace, tree) in trees {
+        for (window, _) in tree.layout_leaves() {
+            let class = window.class_name().unwrap_or_else(|| NO_CLASS.to_string());
+            groups.entry(class).or_default().push(GroupedWindow {
+                workspace: workspace.to_string(),
+                window,
+            });
+        }
+    }
+
+    let mut groups: Vec<ClassGroup> = groups
+        .into_iter()
+        .map(|(class, windows)| ClassGroup { class, windows })
+        .collect();
+    // Stable, so groups of the same size stay sorted by class
+    groups.sort_by_key(|group| std::cmp::Reverse(group.windows.len()));
+    groups
+}
+
+/// The lines of the class view, leaving out the windows of the collapsed groups
+pub(crate) fn rows(groups: &[ClassGroup], collapsed: &HashSet<String>) -> Vec<GroupRow> {
+    let mut rows = vec![];
+    for (index, group) in groups.iter().enumerate() {
+        rows.push(GroupRow::Group(index));
+        if !collapsed.contains(&group.class) {
+            rows.extend((0..group.windows.len()).map(|window| GroupRow::Window(index, window)));
+        }
+    }
+    rows
+}
+
+impl ClassGroup {
+    /// e.g. `▾ Firefox (3)`
+    pub(crate) fn header_str(&self, collapsed: bool) -> String {
+        format!(
+            "{} {} ({})",
+            if collapsed { "▸" } else { "▾" },
+            self.class,
+            self.windows.len()
+        )
+    }
+
+    /// Move every window of the group to `workspace`
+    pub(crate) fn gather(&self, workspace: &str) -> Result<()> {
+        for grouped in self.windows.iter().filter(|w| w.workspace != workspace) {
+            shutils::move_window_to_workspace(grouped.window.id, workspace)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn kill_with(&self, method: KillMethod) -> Result<()> {
+        for grouped in &self.windows {
+            grouped.window.kill_with(method)?;
+        }
+        Ok(())
+    }
+
+    /// The window to focus after the one with con id `focused`, wrapping around
+    pub(crate) fn next_after(&self, focused: Option<u64>) -> Option<&Window> {
+        let position = self
+            .windows
+            .iter()
+            .position(|grouped| Some(grouped.window.id) == focused);
+        let next = position.map_or(0, |position| (position + 1) % self.windows.len());
+        self.windows.get(next).map(|grouped| &grouped.window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::fixture;
+
+    fn leaf(id: u64, class: Option<&str>) -> Window {
+        fixture::window(id, class, &format!("window {}", id))
+    }
+
+    fn workspace(nodes: Vec<Window>) -> Window {
+        fixture::workspace("splith", nodes)
+    }
+
+    #[test]
+    fn group_windows_across_workspaces() {
+        let one = workspace(vec![leaf(1, Some("Alacritty")), leaf(2, Some("Firefox"))]);
+        let two = workspace(vec![leaf(3, Some("Firefox")), leaf(4, None)]);
+        let groups = group_by_class([("1", &one), ("2", &two)].into_iter());
+
+        let summary: Vec<(&str, usize)> = groups
+            .iter()
+            .map(|group| (group.class.as_str(), group.windows.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("Firefox", 2), ("(no class)", 1), ("Alacritty", 1)]
+        );
+        assert_eq!(groups[0].windows[1].workspace, "2");
+        assert_eq!(groups[0].next_after(Some(3)).map(|w| w.id), Some(2));
+        assert_eq!(groups[0].next_after(None).map(|w| w.id), Some(2));
+
+        let collapsed = HashSet::from(["Firefox".to_string()]);
+        assert_eq!(
+            rows(&groups, &collapsed),
+            vec![
+                GroupRow::Group(0),
+                GroupRow::Group(1),
+                GroupRow::Window(1, 0),
+                GroupRow::Group(2),
+                GroupRow::Window(2, 0),
+            ]
+        );
+    }
+}
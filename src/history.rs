@@ -0,0 +1,95 @@
+//! Most-recently-used history of focused windows, to alt-tab across workspaces.
+
+use crate::prelude::*;
+use crate::shutils::i3_cmd;
+use crate::state;
+use crate::window::Window;
+
+const HISTORY_FILE: &str = "history.json";
+const MAX_HISTORY: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct HistoryEntry {
+    /// The container id that i3 uses
+    pub(crate) id: u64,
+    pub(crate) class: Option<String>,
+    pub(crate) name: Option<String>,
+}
+
+impl HistoryEntry {
+    pub(crate) fn name_str(&self) -> String {
+        format!(
+            "[{:15}] {}",
+            self.class.clone().unwrap_or_default(),
+            self.name.clone().unwrap_or_default()
+        )
+    }
+
+    pub(crate) fn focus(&self) -> Result<String> {
+        i3_cmd(&[&format!(r#"[con_id="{}"]"#, self.id), "focus"])
+    }
+}
+
+/// Focused windows, most recent first.
+///
+/// `cursor` points at the entry we last jumped to when walking through the history, so that
+/// repeated `previous` calls keep going back in time instead of bouncing between two windows.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct History {
+    entries: Vec<HistoryEntry>,
+    cursor: usize,
+}
+
+impl History {
+    /// Load the history saved by a previous session
+    pub(crate) fn load() -> Self {
+        state::load(HISTORY_FILE)
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        state::save(HISTORY_FILE, self)
+    }
+
+    pub(crate) fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Record that `window` got focused. Returns true when the history changed.
+    pub(crate) fn record(&mut self, window: &Window) -> bool {
+        // Focus events caused by walking through the history shouldn't reorder it
+        if self.entries.get(self.cursor).map(|e| e.id) == Some(window.id) {
+            return false;
+        }
+
+        self.entries.retain(|entry| entry.id != window.id);
+        self.entries.insert(
+            0,
+            HistoryEntry {
+                id: window.id,
+                class: window.class_name(),
+                name: window.name.clone(),
+            },
+        );
+        self.entries.truncate(MAX_HISTORY);
+        self.cursor = 0;
+        true
+    }
+
+    /// Move the cursor to an older (or newer) entry that still exists, dropping the ones whose
+    /// container is gone.
+    pub(crate) fn step(&mut self, older: bool, alive: &[u64]) -> Option<HistoryEntry> {
+        self.entries.retain(|entry| alive.contains(&entry.id));
+        if self.entries.is_empty() {
+            self.cursor = 0;
+            return None;
+        }
+
+        let cursor = self.cursor.min(self.entries.len() - 1);
+        self.cursor = if older {
+            (cursor + 1).min(self.entries.len() - 1)
+        } else {
+            cursor.saturating_sub(1)
+        };
+        self.entries.get(self.cursor).cloned()
+    }
+}
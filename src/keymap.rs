@@ -0,0 +1,413 @@
+//! Key bindings of the TUI.
+//!
+//! Bindings are kept in tables rather than in `match` statements so that the help overlay and
+//! the hint bar are generated from the very keymap used to dispatch the key presses.
+
+use crate::model::{AppScreen, HomeLayoutSelectedWindow as Pane, InputMode, Message};
+use crate::process::KillMethod;
+use crate::window::{ContainerCommand, Dimension, MoveDirection};
+use ratatui::crossterm::event::KeyCode;
+
+pub(crate) struct Binding {
+    pub(crate) keys: Vec<KeyCode>,
+    pub(crate) message: Message,
+    pub(crate) description: &'static str,
+    /// Panes of the home layout the binding acts on, empty when it works in all of them
+    pub(crate) panes: Vec<Pane>,
+}
+
+impl Binding {
+    fn new(keys: &[KeyCode], message: Message, description: &'static str) -> Self {
+        Binding {
+            keys: keys.to_vec(),
+            message,
+            description,
+            panes: vec![],
+        }
+    }
+
+    fn on(mut self, panes: &[Pane]) -> Self {
+        self.panes = panes.to_vec();
+        self
+    }
+
+    pub(crate) fn applies_to(&self, pane: Pane) -> bool {
+        self.panes.is_empty() || self.panes.contains(&pane)
+    }
+
+    /// e.g. `j/Down`
+    pub(crate) fn keys_str(&self) -> String {
+        self.keys.iter().map(key_str).collect::<Vec<_>>().join("/")
+    }
+}
+
+fn key_str(code: &KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+fn ch(c: char) -> KeyCode {
+    KeyCode::Char(c)
+}
+
+/// Bindings of the normal mode that work on every screen
+fn common() -> Vec<Binding> {
+    let mut bindings = vec![
+        Binding::new(&[ch('?')], Message::ShowHelp, "help"),
+        Binding::new(&[ch('q')], Message::Quit, "quit"),
+        Binding::new(
+            &[KeyCode::Esc],
+            Message::CancelToOrigin,
+            "back to the original focus",
+        ),
+        Binding::new(&[ch('r')], Message::RefreshModel, "refresh"),
+        Binding::new(&[KeyCode::Tab], Message::NextScreen, "next screen"),
+        Binding::new(&[KeyCode::BackTab], Message::PrevScreen, "previous screen"),
+    ];
+    for (index, screen) in AppScreen::ALL.into_iter().enumerate() {
+        let digit = char::from_digit(index as u32 + 1, 10).unwrap_or('?');
+        bindings.push(Binding::new(
+            &[ch(digit)],
+            Message::SwitchScreen(screen),
+            screen.title(),
+        ));
+    }
+    bindings.extend([
+        Binding::new(&[ch('p')], Message::HistoryPrevious, "previous in history"),
+        Binding::new(&[ch('n')], Message::HistoryNext, "next in history"),
+        Binding::new(&[ch('R')], Message::ShowRules, "rules"),
+        Binding::new(&[ch('t')], Message::OpenTemplatePicker, "templates"),
+        Binding::new(&[ch(':')], Message::OpenCommandLine, "i3 command"),
+        Binding::new(&[ch('v')], Message::ShowLog, "toggle log"),
+        Binding::new(&[ch('E')], Message::ExportTree, "export tree"),
+        Binding::new(&[ch('C')], Message::OpenCleanup, "cleanup"),
+    ]);
+    bindings
+}
+
+fn home() -> Vec<Binding> {
+    use Pane::*;
+
+    vec![
+        Binding::new(&[ch('k')], Message::MoveUp, "previous item"),
+        Binding::new(&[ch('j')], Message::MoveDown, "next item"),
+        Binding::new(&[ch('h')], Message::MoveLeft, "pane left"),
+        Binding::new(&[ch('l')], Message::MoveRight, "pane right"),
+        Binding::new(&[ch('H')], Message::MoveLeftMenu, "pane left"),
+        Binding::new(&[ch('J')], Message::MoveDownMenu, "pane down"),
+        Binding::new(&[ch('K')], Message::MoveUpMenu, "pane up"),
+        Binding::new(&[ch('L')], Message::MoveRightMenu, "pane right"),
+        Binding::new(&[ch('z')], Message::ToggleZoom, "zoom pane"),
+        Binding::new(
+            &[ch('>')],
+            Message::ResizePane(Dimension::Width, true),
+            "wider pane",
+        ),
+        Binding::new(
+            &[ch('<')],
+            Message::ResizePane(Dimension::Width, false),
+            "narrower pane",
+        ),
+        Binding::new(
+            &[ch('+')],
+            Message::ResizePane(Dimension::Height, true),
+            "taller pane",
+        ),
+        Binding::new(
+            &[ch('-')],
+            Message::ResizePane(Dimension::Height, false),
+            "shorter pane",
+        ),
+        Binding::new(&[KeyCode::Enter], Message::GoTo, "go to")
+            .on(&[Workspaces, Attached, History]),
+        Binding::new(&[ch('a')], Message::Add, "add workspace").on(&[Workspaces]),
+        Binding::new(&[ch('c')], Message::Consolidate, "consolidate here").on(&[Workspaces]),
+        Binding::new(&[ch('s')], Message::CycleWorkspaceSort, "sort by...").on(&[Workspaces]),
+        Binding::new(&[ch('e')], Message::EditWorkspaceLabel, "label").on(&[Workspaces]),
+        Binding::new(&[ch('i')], Message::EditWorkspaceNote, "note").on(&[Workspaces]),
+        Binding::new(&[ch('b')], Message::PushWorkspaceLabel, "label in i3bar").on(&[Workspaces]),
+        Binding::new(
+            &[ch('w')],
+            Message::OpenWorkspaceSwapPicker,
+            "swap windows with...",
+        )
+        .on(&[Workspaces]),
+        Binding::new(&[ch('d')], Message::Delete, "close").on(&[Attached]),
+        Binding::new(
+            &[ch('D')],
+            Message::Kill(KillMethod::KillClient),
+            "kill client",
+        )
+        .on(&[Attached]),
+        Binding::new(&[ch('T')], Message::Kill(KillMethod::Terminate), "SIGTERM").on(&[Attached]),
+        Binding::new(&[ch('X')], Message::Kill(KillMethod::ForceKill), "SIGKILL").on(&[Attached]),
+        Binding::new(&[ch('w')], Message::EnterLayoutMode, "layout mode").on(&[Attached, Focused]),
+        Binding::new(&[ch('m')], Message::StartGrab, "grab").on(&[Attached]),
+        Binding::new(&[ch('g')], Message::ToggleClassView, "group by class").on(&[Attached]),
+        Binding::new(&[ch('G')], Message::GatherClass, "gather class here").on(&[Attached]),
+        Binding::new(&[ch('x')], Message::CloseClass, "close class").on(&[Attached]),
+        Binding::new(&[ch('f')], Message::CycleClass, "cycle focus in class").on(&[Attached]),
+        Binding::new(&[ch('S')], Message::Screenshot, "screenshot")
+            .on(&[Workspaces, Attached, Focused]),
+    ]
+}
+
+/// Bindings specific to a screen
+fn screen(screen: AppScreen) -> Vec<Binding> {
+    match screen {
+        AppScreen::Home => home(),
+        AppScreen::Scratchpad => vec![
+            Binding::new(&[ch('k'), KeyCode::Up], Message::MoveUp, "previous window"),
+            Binding::new(&[ch('j'), KeyCode::Down], Message::MoveDown, "next window"),
+            Binding::new(&[KeyCode::Enter], Message::GoTo, "show"),
+        ],
+        AppScreen::Log => vec![
+            Binding::new(&[ch('k'), KeyCode::Up], Message::MoveUp, "older"),
+            Binding::new(&[ch('j'), KeyCode::Down], Message::MoveDown, "newer"),
+        ],
+        AppScreen::Tree | AppScreen::Outputs => vec![
+            Binding::new(&[ch('k'), KeyCode::Up], Message::MoveUp, "scroll up"),
+            Binding::new(&[ch('j'), KeyCode::Down], Message::MoveDown, "scroll down"),
+        ],
+    }
+}
+
+fn layout() -> Vec<Binding> {
+    let container = |c: char, command: ContainerCommand, description: &'static str| {
+        Binding::new(&[ch(c)], Message::Container(command), description)
+    };
+
+    vec![
+        container(
+            'h',
+            ContainerCommand::Move(MoveDirection::Left),
+            "move left",
+        ),
+        container(
+            'j',
+            ContainerCommand::Move(MoveDirection::Down),
+            "move down",
+        ),
+        container('k', ContainerCommand::Move(MoveDirection::Up), "move up"),
+        container(
+            'l',
+            ContainerCommand::Move(MoveDirection::Right),
+            "move right",
+        ),
+        Binding::new(
+            &[ch('H')],
+            Message::Resize {
+                grow: false,
+                dimension: Dimension::Width,
+            },
+            "shrink width",
+        ),
+        Binding::new(
+            &[ch('J')],
+            Message::Resize {
+                grow: false,
+                dimension: Dimension::Height,
+            },
+            "shrink height",
+        ),
+        Binding::new(
+            &[ch('K')],
+            Message::Resize {
+                grow: true,
+                dimension: Dimension::Height,
+            },
+            "grow height",
+        ),
+        Binding::new(
+            &[ch('L')],
+            Message::Resize {
+                grow: true,
+                dimension: Dimension::Width,
+            },
+            "grow width",
+        ),
+        container('b', ContainerCommand::SplitHorizontal, "split horizontally"),
+        container('v', ContainerCommand::SplitVertical, "split vertically"),
+        container('t', ContainerCommand::LayoutTabbed, "tabbed"),
+        container('s', ContainerCommand::LayoutStacking, "stacking"),
+        container('x', ContainerCommand::LayoutSplitH, "splith"),
+        container('y', ContainerCommand::LayoutSplitV, "splitv"),
+        container('e', ContainerCommand::LayoutToggleSplit, "toggle split"),
+        container('f', ContainerCommand::ToggleFullscreen, "fullscreen"),
+        container('S', ContainerCommand::ToggleSticky, "sticky"),
+        container(' ', ContainerCommand::ToggleFloating, "floating"),
+        Binding::new(&[ch('w')], Message::OpenSwapPicker, "swap with..."),
+        Binding::new(&[KeyCode::Esc], Message::Cancel, "leave (or any other key)"),
+    ]
+}
+
+fn picker() -> Vec<Binding> {
+    vec![
+        Binding::new(&[ch('j'), KeyCode::Down], Message::PickerDown, "next"),
+        Binding::new(&[ch('k'), KeyCode::Up], Message::PickerUp, "previous"),
+        Binding::new(&[KeyCode::Enter], Message::PickerConfirm, "confirm"),
+        Binding::new(&[KeyCode::Esc, ch('q')], Message::Cancel, "cancel"),
+    ]
+}
+
+fn cleanup() -> Vec<Binding> {
+    vec![
+        Binding::new(&[ch('j'), KeyCode::Down], Message::PickerDown, "next"),
+        Binding::new(&[ch('k'), KeyCode::Up], Message::PickerUp, "previous"),
+        Binding::new(&[ch(' ')], Message::CleanupToggle, "check"),
+        Binding::new(&[ch('d')], Message::CleanupClose, "close checked"),
+        Binding::new(&[ch('m')], Message::CleanupMove, "move checked"),
+        Binding::new(&[KeyCode::Esc, ch('q')], Message::Cancel, "cancel"),
+    ]
+}
+
+fn confirm() -> Vec<Binding> {
+    vec![
+        Binding::new(&[ch('y')], Message::ConfirmYes, "yes"),
+        Binding::new(
+            &[ch('n'), KeyCode::Esc],
+            Message::Cancel,
+            "no (or any other key)",
+        ),
+    ]
+}
+
+fn grab() -> Vec<Binding> {
+    vec![
+        Binding::new(&[ch('j'), KeyCode::Down], Message::GrabDown, "move down"),
+        Binding::new(&[ch('k'), KeyCode::Up], Message::GrabUp, "move up"),
+        Binding::new(&[ch('J')], Message::GrabNextWorkspace, "next workspace"),
+        Binding::new(&[ch('K')], Message::GrabPrevWorkspace, "previous workspace"),
+        Binding::new(&[KeyCode::Enter], Message::Drop, "drop"),
+        Binding::new(&[KeyCode::Esc, ch('q')], Message::Cancel, "cancel"),
+    ]
+}
+
+fn rules() -> Vec<Binding> {
+    vec![
+        Binding::new(&[ch('r')], Message::ReloadRules, "reload"),
+        Binding::new(&[ch('e')], Message::EditRules, "edit"),
+        Binding::new(&[KeyCode::Esc, ch('q'), ch('R')], Message::Cancel, "close"),
+    ]
+}
+
+fn command() -> Vec<Binding> {
+    vec![
+        Binding::new(&[KeyCode::Enter], Message::CommandRun, "run"),
+        Binding::new(&[KeyCode::Tab], Message::CommandComplete, "complete"),
+        Binding::new(&[KeyCode::Up], Message::CommandOlder, "older command"),
+        Binding::new(&[KeyCode::Down], Message::CommandNewer, "newer command"),
+        Binding::new(&[KeyCode::Backspace], Message::CommandBackspace, "delete"),
+        Binding::new(&[KeyCode::Esc], Message::Cancel, "cancel"),
+    ]
+}
+
+fn text() -> Vec<Binding> {
+    vec![
+        Binding::new(&[KeyCode::Enter], Message::TextConfirm, "save"),
+        Binding::new(&[KeyCode::Backspace], Message::CommandBackspace, "delete"),
+        Binding::new(&[KeyCode::Esc], Message::Cancel, "cancel"),
+    ]
+}
+
+fn help() -> Vec<Binding> {
+    vec![
+        Binding::new(
+            &[ch('j'), KeyCode::Down],
+            Message::PickerDown,
+            "scroll down",
+        ),
+        Binding::new(&[ch('k'), KeyCode::Up], Message::PickerUp, "scroll up"),
+        Binding::new(&[KeyCode::Esc, ch('q'), ch('?')], Message::Cancel, "close"),
+    ]
+}
+
+/// The bindings active in an input mode, on a given screen
+pub(crate) fn for_mode(mode: &InputMode, current: AppScreen) -> Vec<Binding> {
+    match mode {
+        InputMode::Normal => common().into_iter().chain(screen(current)).collect(),
+        InputMode::Layout => layout(),
+        InputMode::SwapPicker { .. }
+        | InputMode::WorkspaceSwapPicker { .. }
+        | InputMode::TemplatePicker { .. }
+        | InputMode::ContextMenu { .. } => picker(),
+        InputMode::Grab { .. } => grab(),
+        InputMode::Rules => rules(),
+        InputMode::Cleanup { .. } => cleanup(),
+        InputMode::Confirm { .. } => confirm(),
+        InputMode::Help { .. } => help(),
+        InputMode::Command { .. } => command(),
+        InputMode::Text { .. } => text(),
+    }
+}
+
+/// Message bound to `code`, skipping bindings of panes other than the selected one
+pub(crate) fn lookup(bindings: &[Binding], code: KeyCode, pane: Option<Pane>) -> Option<Message> {
+    bindings
+        .iter()
+        .filter(|binding| binding.panes.is_empty() || pane.is_some_and(|p| binding.applies_to(p)))
+        .find(|binding| binding.keys.contains(&code))
+        .map(|binding| binding.message.clone())
+}
+
+/// Every binding, grouped by the pane or mode they are active in
+pub(crate) fn help_sections() -> Vec<(String, Vec<Binding>)> {
+    let home_global = home().into_iter().filter(|b| b.panes.is_empty()).collect();
+
+    let mut sections = vec![
+        ("Everywhere".to_string(), common()),
+        ("Home screen".to_string(), home_global),
+    ];
+    for pane in Pane::ALL {
+        let bindings: Vec<Binding> = home()
+            .into_iter()
+            .filter(|b| !b.panes.is_empty() && b.applies_to(pane))
+            .collect();
+        if !bindings.is_empty() {
+            sections.push((format!("{} pane", pane.title()), bindings));
+        }
+    }
+
+    sections.push((
+        "Tree and Outputs screens".to_string(),
+        screen(AppScreen::Tree),
+    ));
+    sections.push((
+        "Scratchpad screen".to_string(),
+        screen(AppScreen::Scratchpad),
+    ));
+    sections.push(("Log screen".to_string(), screen(AppScreen::Log)));
+
+    sections.push(("Layout mode".to_string(), layout()));
+    sections.push(("Grab mode".to_string(), grab()));
+    sections.push(("Pickers and menus".to_string(), picker()));
+    sections.push(("Rules".to_string(), rules()));
+    sections.push(("Cleanup".to_string(), cleanup()));
+    sections.push(("Confirmation".to_string(), confirm()));
+    sections.push(("Command line".to_string(), command()));
+    sections.push(("Labels and notes".to_string(), text()));
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_respects_the_pane() {
+        let bindings = for_mode(&InputMode::Normal, AppScreen::Home);
+
+        assert!(matches!(
+            lookup(&bindings, ch('w'), Some(Pane::Attached)),
+            Some(Message::EnterLayoutMode)
+        ));
+        assert!(matches!(
+            lookup(&bindings, ch('w'), Some(Pane::Workspaces)),
+            Some(Message::OpenWorkspaceSwapPicker)
+        ));
+        assert!(lookup(&bindings, ch('d'), Some(Pane::Workspaces)).is_none());
+    }
+}
@@ -0,0 +1,165 @@
+//! Log of what happened during a session: status messages, the i3 commands we sent and the
+//! replies i3 gave back.
+//!
+//! Entries are kept in memory for the log panel and, when enabled in the settings, appended to
+//! `$XDG_STATE_HOME/i3-mgr/i3-mgr.log`.
+
+use crate::prelude::*;
+use crate::state;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LOG_FILE: &str = "i3-mgr.log";
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub(crate) enum Level {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warning => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct LogEntry {
+    /// Local time, e.g. `2024-05-12 18:03:27.512`
+    pub(crate) time: String,
+    pub(crate) level: Level,
+    pub(crate) message: String,
+}
+
+impl LogEntry {
+    /// Time of the day the entry was recorded at, without the date
+    pub(crate) fn clock(&self) -> &str {
+        self.time
+            .split_once(' ')
+            .map_or(&self.time, |(_, clock)| clock)
+    }
+
+    pub(crate) fn log_str(&self) -> String {
+        format!("{} {:5} {}", self.time, self.level.as_str(), self.message)
+    }
+}
+
+struct Log {
+    entries: VecDeque<LogEntry>,
+    file: Option<File>,
+}
+
+static LOG: Mutex<Log> = Mutex::new(Log {
+    entries: VecDeque::new(),
+    file: None,
+});
+
+/// Format a time as `YYYY-MM-DD HH:MM:SS.mmm` in the local timezone, as it is at that time
+fn format_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() as libc::time_t;
+    // SAFETY: tm is plain data, which localtime_r fills in or leaves alone on failure
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&seconds, &mut tm) };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// The current local time, e.g. `2024-05-12 18:03:27.512`
+pub(crate) fn now() -> String {
+    format_time(SystemTime::now())
+}
+
+/// Start appending the entries to the log file, if asked to.
+pub(crate) fn init(to_file: bool) -> Result<()> {
+    let file = if to_file {
+        std::fs::create_dir_all(state::state_dir())?;
+        Some(
+            File::options()
+                .create(true)
+                .append(true)
+                .open(state::state_file(LOG_FILE))?,
+        )
+    } else {
+        None
+    };
+
+    LOG.lock().unwrap().file = file;
+    Ok(())
+}
+
+pub(crate) fn record(level: Level, message: impl Into<String>) {
+    let entry = LogEntry {
+        time: now(),
+        level,
+        message: message.into(),
+    };
+
+    let mut log = LOG.lock().unwrap();
+    if let Some(file) = &mut log.file {
+        let _ = writeln!(file, "{}", entry.log_str());
+    }
+    log.entries.push_back(entry);
+    if log.entries.len() > MAX_ENTRIES {
+        log.entries.pop_front();
+    }
+}
+
+/// Number of entries kept in memory
+pub(crate) fn len() -> usize {
+    LOG.lock().unwrap().entries.len()
+}
+
+/// The `count` entries preceding the `skip` most recent ones, oldest first
+pub(crate) fn tail(count: usize, skip: usize) -> Vec<LogEntry> {
+    let log = LOG.lock().unwrap();
+    let end = log.entries.len().saturating_sub(skip);
+    let start = end.saturating_sub(count);
+    log.entries.range(start..end).cloned().collect()
+}
+
+/// The most recent entry
+pub(crate) fn last() -> Option<LogEntry> {
+    LOG.lock().unwrap().entries.back().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn format_local_times() {
+        // Whatever the timezone, only whole minutes separate it from UTC
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_251_199_042);
+        let entry = LogEntry {
+            time: format_time(time),
+            level: Level::Info,
+            message: "hello".to_string(),
+        };
+        assert_eq!(entry.time.len(), "2024-02-29 23:59:59.042".len());
+        assert!(entry.time.ends_with(":59.042"), "{}", entry.time);
+        assert_eq!(entry.clock(), &entry.time[11..]);
+        assert!(entry.log_str().ends_with(" INFO  hello"));
+    }
+}
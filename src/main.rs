@@ -1,6 +1,28 @@
+pub(crate) mod bar;
+pub(crate) mod cleanup;
+pub(crate) mod cli;
+pub(crate) mod config;
+pub(crate) mod daemon;
+pub(crate) mod events;
+pub(crate) mod export;
+pub(crate) mod groups;
+pub(crate) mod history;
+pub(crate) mod keymap;
+pub(crate) mod log;
 pub mod model;
+pub(crate) mod notes;
+pub(crate) mod output;
+pub(crate) mod palette;
+pub(crate) mod panes;
+pub(crate) mod popup;
 pub(crate) mod prelude;
+pub(crate) mod process;
+pub(crate) mod rules;
+pub(crate) mod screenshot;
 pub(crate) mod shutils;
+pub(crate) mod state;
+pub(crate) mod swap;
+pub(crate) mod template;
 pub mod window;
 pub(crate) mod workspace;
 pub mod x11window;
@@ -9,7 +31,7 @@ use prelude::*;
 
 use std::io::stdout;
 
-use model::{Model, RunningState};
+use model::{Message, Model, RunningState};
 use prelude::Result;
 use std::io::Stdout;
 
@@ -20,21 +42,54 @@ pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 pub fn init_terminal() -> Result<Tui> {
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
+    stdout().execute(EnableMouseCapture)?;
     let terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
     Ok(terminal)
 }
 
 /// Restore the terminal to its original state
 pub fn restore_terminal() -> Result<()> {
+    stdout().execute(DisableMouseCapture)?;
     stdout().execute(LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
 }
 
+/// Open a file in the user's editor, creating it (and its directory) if needed
+fn edit_file(path: &std::path::Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if !path.exists() {
+        std::fs::write(path, "[]\n")?;
+    }
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+    let status = std::process::Command::new(&editor).arg(path).status()?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", editor, status).into());
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(err) = cli::run(&args) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let settings = config::Settings::load()?;
+    log::init(settings.log_file)?;
+
     let mut terminal = init_terminal()?;
     let frame = terminal.get_frame();
-    let mut model = Model::new(&frame);
+    let mut model = Model::new(&frame, settings);
 
     while model.running_state != RunningState::Done {
         // Render the current view
@@ -47,6 +102,22 @@ fn main() -> Result<()> {
         while current_msg.is_some() {
             current_msg = model.update(current_msg.unwrap());
         }
+
+        // Hand the terminal over to the editor when a file needs editing
+        if let Some(path) = model.take_pending_edit() {
+            restore_terminal()?;
+            let outcome = edit_file(&path);
+            terminal = init_terminal()?;
+            terminal.clear()?;
+            match outcome {
+                Ok(()) => {
+                    model.update(Message::ReloadRules);
+                }
+                Err(err) => {
+                    model.report_error(format!("Unable to edit {}: {}", path.display(), err))
+                }
+            }
+        }
     }
 
     restore_terminal()?;
@@ -6,15 +6,217 @@ pub(crate) enum RunningState {
     Done,
 }
 
-#[derive(Copy, Clone, Debug)]
-enum HomeLayoutSelectedWindow {
+/// A destructive action, run once confirmed
+#[derive(Clone, Debug)]
+pub(crate) enum PendingAction {
+    /// Apply a fix to the checked findings of a cleanup
+    Cleanup(Vec<Finding>, Fix),
+    /// Close every window of a class
+    CloseClass(ClassGroup),
+}
+
+/// How key presses are currently interpreted
+#[derive(Clone, Debug)]
+pub(crate) enum InputMode {
+    Normal,
+    /// Waiting for layout manipulation keys, applied to the selected window
+    Layout,
+    /// Picking the window that `source` should be swapped with
+    SwapPicker {
+        source: Window,
+        index: usize,
+    },
+    /// Picking the workspace whose windows get exchanged with those of `source`
+    WorkspaceSwapPicker {
+        source: String,
+        index: usize,
+    },
+    /// Reviewing the findings of a cleanup scan, the checked ones getting closed or moved to
+    /// `target`
+    Cleanup {
+        findings: Vec<Finding>,
+        checked: Vec<bool>,
+        index: usize,
+        target: String,
+    },
+    /// Waiting for `y` before running `action`
+    Confirm {
+        prompt: String,
+        action: PendingAction,
+    },
+    /// Carrying `window` around the Attached and Workspaces panes, ready to be dropped at the
+    /// selected position. The origin indices are restored when cancelling.
+    Grab {
+        window: Window,
+        origin_workspace: u64,
+        origin_index: u64,
+    },
+    /// Showing the rules pane
+    Rules,
+    /// Picking the template to apply
+    TemplatePicker {
+        names: Vec<String>,
+        index: usize,
+    },
+    /// Typing an i3 command
+    Command {
+        input: String,
+        completion: Option<Completion>,
+        /// Position in the command history when walking through it
+        history_index: Option<usize>,
+    },
+    /// Showing the help overlay, scrolled down by `scroll` lines
+    Help {
+        scroll: u16,
+    },
+    /// Typing the label or note of a workspace
+    Text {
+        field: TextField,
+        input: String,
+    },
+    /// Actions of a pane, opened with a right click at `anchor` (column, row)
+    ContextMenu {
+        items: Vec<(String, Message)>,
+        index: usize,
+        anchor: (u16, u16),
+    },
+}
+
+/// What the text typed in the status bar is for, along with the workspace it applies to
+#[derive(Clone, Debug)]
+pub(crate) enum TextField {
+    Label(String),
+    Note(String),
+}
+
+impl TextField {
+    fn prompt(&self) -> String {
+        match self {
+            TextField::Label(workspace) => format!("Label of {}: ", workspace),
+            TextField::Note(workspace) => format!("Note of {}: ", workspace),
+        }
+    }
+}
+
+/// What the cursors of the home screen point at, by con id, to find it again after a refresh
+struct Selection {
+    workspace: Option<u64>,
+    window: Option<u64>,
+    history: Option<u64>,
+}
+
+/// The new position of the item `id` among `ids` or, when it is gone, the position of the
+/// neighbour that took its place (the previous item when it was the last one).
+fn follow(ids: &[u64], id: Option<u64>, position: u64) -> u64 {
+    id.and_then(|id| ids.iter().position(|i| *i == id))
+        .map_or(position.min(ids.len().saturating_sub(1) as u64), |p| {
+            p as u64
+        })
+}
+
+/// Where the focus was when the TUI got opened (or the popup shown), to go back there on cancel
+#[derive(Debug, Clone, PartialEq)]
+struct Origin {
+    workspace: String,
+    window: Option<u64>,
+}
+
+impl Origin {
+    /// The focused workspace and the most recently focused window that isn't the TUI's own
+    fn capture(workspaces: &[Workspace], history: &History, own_window: Option<u64>) -> Self {
+        Origin {
+            workspace: workspaces
+                .iter()
+                .find(|ws| ws.focused)
+                .map(|ws| ws.name.clone())
+                .unwrap_or_default(),
+            window: history
+                .entries()
+                .iter()
+                .map(|entry| entry.id)
+                .find(|id| Some(*id) != own_window),
+        }
+    }
+
+    fn restore(&self) -> Result<String> {
+        if !self.workspace.is_empty() {
+            i3_cmd(&["workspace", &quoted(&self.workspace)])?;
+        }
+        match self.window {
+            Some(id) => i3_cmd(&[&format!(r#"[con_id="{}"]"#, id), "focus"]),
+            None => Ok(String::new()),
+        }
+    }
+}
+
+/// Maximum delay between two clicks for them to count as a double-click
+const DOUBLE_CLICK_DELAY: Duration = Duration::from_millis(400);
+
+fn rect_contains(rect: Rect, column: u16, row: u16) -> bool {
+    column >= rect.x && column < rect.x + rect.width && row >= rect.y && row < rect.y + rect.height
+}
+
+/// The screen whose tab is drawn at `column`, counted from the start of the tab bar
+fn tab_at(column: u16) -> Option<AppScreen> {
+    // Tabs are drawn as ` title │ title │ ...`
+    let mut end = 0;
+    for screen in AppScreen::ALL {
+        end += screen.tab_title().len() as u16 + 3;
+        if column < end {
+            return Some(screen);
+        }
+    }
+    None
+}
+
+/// Where a context menu opened at `anchor` is drawn, kept inside `area`
+fn context_menu_rect(anchor: (u16, u16), items: &[(String, Message)], area: Rect) -> Rect {
+    let width = (items
+        .iter()
+        .map(|(label, _)| label.len())
+        .max()
+        .unwrap_or(0) as u16
+        + 4)
+    .max(12)
+    .min(area.width);
+    let height = (items.len() as u16 + 2).min(area.height);
+
+    Rect::new(
+        anchor.0.min(area.x + area.width - width),
+        anchor.1.min(area.y + area.height - height),
+        width,
+        height,
+    )
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum HomeLayoutSelectedWindow {
     Workspaces,
     Focused,
     Attached,
     Floating,
+    History,
 }
 
 impl HomeLayoutSelectedWindow {
+    pub(crate) const ALL: [Self; 5] = [
+        HomeLayoutSelectedWindow::Workspaces,
+        HomeLayoutSelectedWindow::Focused,
+        HomeLayoutSelectedWindow::History,
+        HomeLayoutSelectedWindow::Attached,
+        HomeLayoutSelectedWindow::Floating,
+    ];
+
+    pub(crate) fn title(&self) -> &'static str {
+        match self {
+            HomeLayoutSelectedWindow::Workspaces => "Workspaces",
+            HomeLayoutSelectedWindow::Focused => "Focused Window",
+            HomeLayoutSelectedWindow::Attached => "Attached Windows",
+            HomeLayoutSelectedWindow::Floating => "Floating Windows",
+            HomeLayoutSelectedWindow::History => "Recent Windows",
+        }
+    }
+
     fn render(frame: &mut Frame, model: &Model, home_layout: &HomeLayout) {
         let mut workspaces_state = ListState::default();
 
@@ -36,18 +238,27 @@ impl HomeLayoutSelectedWindow {
 use crate::shutils;
 use crate::shutils::i3_cmd;
 use crate::shutils::pipe;
+use crate::shutils::quoted;
 
 #[derive(Debug, Clone)]
 struct HomeLayout {
     workspaces: Rect,
+    minimap: Rect,
     focused_window: Rect,
+    history: Rect,
     attached_windows: Rect,
     floating_windows: Rect,
+    /// The screen tabs
+    tabs: Rect,
+    /// Everything between the tabs and the hints, where the panes or other screens go
+    main: Rect,
+    hints: Rect,
     status: Rect,
     selected: HomeLayoutSelectedWindow,
     workspaces_index: u64,
     workspace_state: Option<ListState>,
     attached_windows_index: u64,
+    history_index: u64,
 }
 
 impl HomeLayout {
@@ -76,24 +287,7 @@ impl HomeLayout {
 
     /// Move all windows from all workspaces to a single workspace.
     fn consolidate_workspaces(&mut self, target_workspace: &str, model: &mut Model) -> Result<()> {
-        // Create a command to move all windows over
-        //
-        //
-        //
-
-        for (workspace_name, workspace_nodes) in model.ws_map.iter() {
-            if workspace_name == target_workspace {
-                // Do nothing.
-            } else {
-                let nodes = workspace_nodes
-                    .iter()
-                    .flat_map(|window| window.flatten())
-                    .filter(|ws| ws.name.is_some());
-                for node in nodes {
-                    shutils::move_window_to_workspace(node.id, target_workspace)?;
-                }
-            }
-        }
+        crate::window::consolidate_workspaces(&model.ws_map, target_workspace)?;
 
         self.workspaces_index = 0;
         model.refresh();
@@ -101,35 +295,32 @@ impl HomeLayout {
         Ok(())
     }
 
-    fn show_scratchpad(&self) -> Result<String> {
-        i3_cmd(&["scratchpad", "show"])
-    }
-
     /// Send a message to i3 to create a new message
     fn add_workspace(&mut self, model: &mut Model) -> Result<String> {
-        let _ = i3_cmd(&["workspace", &(self.workspaces_index + 1).to_string()])?;
+        let reply = i3_cmd(&["workspace", &(self.workspaces_index + 1).to_string()])?;
         model.refresh();
-        self.show_scratchpad()
+        model.finish_action(model.settings.after.new_workspace)?;
+        Ok(reply)
     }
 
     fn decrement_attached_index(&mut self, n_windows: usize) {
-        if self.attached_windows_index as usize == 0 {
-            self.attached_windows_index = (n_windows - 1) as u64
+        if self.attached_windows_index == 0 {
+            self.attached_windows_index = n_windows.saturating_sub(1) as u64
         } else {
             self.attached_windows_index -= 1
         }
     }
 
     fn decrement_workspace_index(&mut self, n_workspaces: usize) {
-        if self.workspaces_index as usize == 0 {
-            self.workspaces_index = (n_workspaces - 1) as u64
+        if self.workspaces_index == 0 {
+            self.workspaces_index = n_workspaces.saturating_sub(1) as u64
         } else {
             self.workspaces_index -= 1
         }
     }
 
     fn increment_attached_index(&mut self, n_windows: usize) {
-        if self.attached_windows_index as usize == n_windows - 1 {
+        if n_windows == 0 || self.attached_windows_index as usize >= n_windows - 1 {
             self.attached_windows_index = 0
         } else {
             self.attached_windows_index += 1
@@ -138,25 +329,49 @@ impl HomeLayout {
 
     /// Select the next workspace
     fn increment_workspace_index(&mut self, n_workspaces: usize) {
-        if self.workspaces_index as usize == n_workspaces - 1 {
+        if n_workspaces == 0 || self.workspaces_index as usize >= n_workspaces - 1 {
             self.workspaces_index = 0
         } else {
             self.workspaces_index += 1
         }
     }
 
-    fn move_down_inside(&mut self, n_workspaces: usize, n_windows: usize) {
+    fn increment_history_index(&mut self, n_entries: usize) {
+        if n_entries == 0 || self.history_index as usize >= n_entries - 1 {
+            self.history_index = 0
+        } else {
+            self.history_index += 1
+        }
+    }
+
+    fn decrement_history_index(&mut self, n_entries: usize) {
+        if self.history_index == 0 {
+            self.history_index = n_entries.saturating_sub(1) as u64
+        } else {
+            self.history_index -= 1
+        }
+    }
+
+    fn move_down_inside(&mut self, n_workspaces: usize, n_windows: usize, n_history: usize) {
         match self.selected {
-            HomeLayoutSelectedWindow::Workspaces => self.increment_workspace_index(n_workspaces),
+            HomeLayoutSelectedWindow::Workspaces => {
+                self.increment_workspace_index(n_workspaces);
+                self.attached_windows_index = 0;
+            }
             HomeLayoutSelectedWindow::Attached => self.increment_attached_index(n_windows),
+            HomeLayoutSelectedWindow::History => self.increment_history_index(n_history),
             _ => (),
         }
     }
 
-    fn move_up_inside(&mut self, n_workspaces: usize, n_windows: usize) {
+    fn move_up_inside(&mut self, n_workspaces: usize, n_windows: usize, n_history: usize) {
         match self.selected {
-            HomeLayoutSelectedWindow::Workspaces => self.decrement_workspace_index(n_workspaces),
+            HomeLayoutSelectedWindow::Workspaces => {
+                self.decrement_workspace_index(n_workspaces);
+                self.attached_windows_index = 0;
+            }
             HomeLayoutSelectedWindow::Attached => self.decrement_attached_index(n_windows),
+            HomeLayoutSelectedWindow::History => self.decrement_history_index(n_history),
             _ => (),
         }
     }
@@ -166,6 +381,9 @@ impl HomeLayout {
             HomeLayoutSelectedWindow::Focused => {
                 self.selected = HomeLayoutSelectedWindow::Workspaces;
             }
+            HomeLayoutSelectedWindow::History => {
+                self.selected = HomeLayoutSelectedWindow::Focused;
+            }
             HomeLayoutSelectedWindow::Floating => {
                 self.selected = HomeLayoutSelectedWindow::Attached;
             }
@@ -178,6 +396,9 @@ impl HomeLayout {
             HomeLayoutSelectedWindow::Workspaces => {
                 self.selected = HomeLayoutSelectedWindow::Focused;
             }
+            HomeLayoutSelectedWindow::Focused => {
+                self.selected = HomeLayoutSelectedWindow::History;
+            }
             HomeLayoutSelectedWindow::Attached => {
                 self.selected = HomeLayoutSelectedWindow::Floating;
             }
@@ -190,7 +411,7 @@ impl HomeLayout {
             HomeLayoutSelectedWindow::Workspaces => {
                 self.selected = HomeLayoutSelectedWindow::Attached;
             }
-            HomeLayoutSelectedWindow::Focused => {
+            HomeLayoutSelectedWindow::Focused | HomeLayoutSelectedWindow::History => {
                 self.selected = HomeLayoutSelectedWindow::Floating;
             }
             _ => (),
@@ -223,13 +444,22 @@ impl HomeLayout {
         //     self.workspaces,
         // );
 
-        model.update_status(&format!("Selected workspace: {}", self.workspaces_index));
+        let items = model.workspaces.iter().map(|ws| {
+            let n_windows = model.workspace_windows(&ws.name).map_or(0, |w| w.len());
+            format!(
+                "{}{} ({}){}",
+                ws.indicator(),
+                ws.name,
+                n_windows,
+                model.workspace_notes.describe(ws)
+            )
+        });
 
         frame.render_stateful_widget(
-            List::new(model.workspaces.iter().map(|ws| ws.name.clone()))
+            List::new(items)
                 .block(
                     Block::bordered()
-                        .title("Workspaces")
+                        .title(format!("Workspaces by {}", model.workspace_sort.label()))
                         .border_style(border_style),
                 )
                 .highlight_style(SELECTED_STYLE)
@@ -275,8 +505,10 @@ impl HomeLayout {
                 model
                     .floating_windows
                     .iter()
-                    .filter(|ws| ws.name.is_some())
-                    .map(|ws| ws.name_str()),
+                    .filter(|w| w.is_window())
+                    .map(|w| model.window_str(w))
+                    .chain((!model.docks.is_empty()).then(|| "── Docks and bars ──".to_string()))
+                    .chain(model.docks.iter().map(|w| w.name_str())),
             )
             .block(
                 Block::bordered()
@@ -289,6 +521,28 @@ impl HomeLayout {
         );
     }
 
+    fn render_history(&self, frame: &mut Frame, model: &Model) {
+        let mut state = ListState::default();
+        state.select(Some(self.history_index as usize));
+
+        let border_style = match self.selected {
+            HomeLayoutSelectedWindow::History => Style::new().blue(),
+            _ => Style::new(),
+        };
+
+        frame.render_stateful_widget(
+            List::new(model.history.entries().iter().map(|entry| entry.name_str()))
+                .block(
+                    Block::bordered()
+                        .title("Recent Windows")
+                        .border_style(border_style),
+                )
+                .highlight_style(SELECTED_STYLE),
+            self.history,
+            &mut state,
+        );
+    }
+
     fn render_attached(&self, frame: &mut Frame, model: &mut Model) -> Result<()> {
         let mut state = ListState::default();
         state.select(Some(self.attached_windows_index as usize));
@@ -298,18 +552,31 @@ impl HomeLayout {
             _ => Style::new(),
         };
 
-        frame.render_stateful_widget(
-            List::new(
+        if model.class_view {
+            state.select(Some(model.class_view_index as usize));
+        }
+
+        let (items, title) = match &model.input_mode {
+            _ if model.class_view => (model.class_view_lines(), "Windows by class".to_string()),
+            InputMode::Grab { window, .. } => (
+                model.grab_preview(window),
+                format!(
+                    "Attached Windows (dropping {})",
+                    window.name.clone().unwrap_or_default()
+                ),
+            ),
+            _ => (
                 model
                     .workspace_window_names(&model.selected_workspace())
                     .unwrap(),
-            )
-            .block(
-                Block::bordered()
-                    .title("Attached Windows")
-                    .border_style(border_style),
-            )
-            .highlight_style(SELECTED_STYLE),
+                "Attached Windows".to_string(),
+            ),
+        };
+
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(title).border_style(border_style))
+                .highlight_style(SELECTED_STYLE),
             self.attached_windows,
             &mut state,
         );
@@ -323,42 +590,115 @@ pub enum AppLayout {
 }
 
 use std::fmt::format;
-use std::time::SystemTime;
 
 pub(crate) struct Model {
     windows: Vec<Window>,
     workspaces: Vec<Workspace>,
+    workspace_sort: WorkspaceSort,
+    workspace_notes: WorkspaceNotes,
     attached_windows: Vec<Window>,
     floating_windows: Vec<Window>,
+    /// Dock and bar windows, listed below the floating windows
+    docks: Vec<Window>,
     fcsd_window: Option<Window>,
     ws_map: HashMap<String, Vec<Window>>,
     ws_map_names: HashMap<String, Vec<String>>,
+    ws_trees: HashMap<String, Window>,
     pub(crate) running_state: RunningState,
-    status_msg: String,
-    status_timestamp: SystemTime,
-    startup_time: SystemTime,
+    /// The latest status message
+    status: Option<LogEntry>,
     current_menu: AppLayout,
     home_layout: Option<HomeLayout>,
+    process_monitor: ProcessMonitor,
+    input_mode: InputMode,
+    history: History,
+    window_events: Option<Receiver<WindowEvent>>,
+    rules: RulesEngine,
+    rules_snapshot: RulesSnapshot,
+    pending_edit: Option<PathBuf>,
+    command_history: CommandHistory,
+    screen: AppScreen,
+    /// Screen to go back to when the log is toggled off
+    screen_before_log: AppScreen,
+    /// Scroll offset or selected item of the screens other than home
+    screen_index: usize,
+    pane_sizes: PaneSizes,
+    outputs: Vec<Output>,
+    scratchpad: Vec<Window>,
+    /// Whether the Attached pane shows the windows grouped by class
+    class_view: bool,
+    /// The windows of every workspace grouped by class, as of the last refresh
+    class_groups: Vec<ClassGroup>,
+    /// Classes whose windows are hidden in the class view
+    collapsed_classes: HashSet<String>,
+    /// Selected line of the class view
+    class_view_index: u64,
+    settings: Settings,
+    /// The terminal the TUI runs in, i.e. the window focused when it started, popup or not
+    own_window: Option<u64>,
+    origin: Origin,
+    /// Area of the last drawn frame
+    frame_area: Rect,
+    /// Time and position of the last click, to detect double-clicks
+    last_click: Option<(Instant, u16, u16)>,
 }
 
+use crate::cleanup::{self, Finding, Fix, Issue};
+use crate::config::{self, PostAction, Settings};
+use crate::daemon;
+use crate::events::{self, WindowEvent};
+use crate::export;
+use crate::groups::{self, ClassGroup, GroupRow};
+use crate::history::History;
+use crate::keymap;
+use crate::log::{self, Level, LogEntry};
+use crate::notes::{labelled_name, WorkspaceNotes};
+use crate::output::{list_outputs, Output};
+use crate::palette::{self, CommandHistory, Completion, CompletionSource};
+use crate::panes::PaneSizes;
+use crate::popup;
 use crate::prelude::*;
+use crate::process::{KillMethod, ProcessMonitor};
+use crate::rules::{RulesEngine, RulesSnapshot, RULES_FILE};
+use crate::screenshot::{self, ScreenshotOptions};
 use crate::shutils::cmd;
+use crate::swap;
+use crate::template::{apply_template, load_templates, TEMPLATES_FILE};
 use crate::window::*;
-use crate::workspace::Workspace;
+use crate::workspace::{self, Workspace, WorkspaceSort};
 
 use ratatui::layout::Rect;
+use ratatui::widgets::canvas::{Canvas, Rectangle};
+use ratatui::widgets::Clear;
 use ratatui::widgets::ListState;
+use ratatui::widgets::Tabs;
 use ratatui::widgets::Wrap;
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode},
+    crossterm::event::{self, Event, KeyCode, MouseButton, MouseEvent, MouseEventKind},
     widgets::Paragraph,
     Frame,
 };
-use std::time::Duration;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
 
+#[derive(Clone, Debug)]
 pub(crate) enum Message {
     Quit,
-    ShowKey,
+    /// Go back to the workspace and window focused when the TUI was opened
+    CancelToOrigin,
+    /// Show every key binding
+    ShowHelp,
+    /// Show the log of status messages and i3 commands
+    ShowLog,
+    SwitchScreen(AppScreen),
+    NextScreen,
+    PrevScreen,
+    /// Show only the selected pane, or all of them again
+    ToggleZoom,
+    /// Grow (or shrink) the selected pane
+    ResizePane(Dimension, bool),
     RefreshModel,
     MoveUp,
     MoveRight,
@@ -376,49 +716,225 @@ pub(crate) enum Message {
     GoTo,
     /// Delete a workspace or window
     Delete,
+    /// Get rid of the selected window with a specific method
+    Kill(KillMethod),
+    /// Start interpreting keys as layout manipulation commands
+    EnterLayoutMode,
+    /// Apply a layout command to the selected window
+    Container(ContainerCommand),
+    /// Resize the selected window by the configured step
+    Resize {
+        grow: bool,
+        dimension: Dimension,
+    },
+    /// Choose a window to swap the selected one with
+    OpenSwapPicker,
+    OpenWorkspaceSwapPicker,
+    /// Scan for empty workspaces and left-behind windows
+    OpenCleanup,
+    CleanupToggle,
+    /// Close the checked findings
+    CleanupClose,
+    /// Move the checked windows to the target workspace
+    CleanupMove,
+    /// Run the action waiting for a confirmation
+    ConfirmYes,
+    /// Save the selected window, or every window of the selected workspace, as PNG
+    Screenshot,
+    /// Choose a workspace template to apply
+    OpenTemplatePicker,
+    PickerUp,
+    PickerDown,
+    PickerConfirm,
+    /// Pick up the selected attached window to move it elsewhere
+    StartGrab,
+    GrabUp,
+    GrabDown,
+    GrabNextWorkspace,
+    GrabPrevWorkspace,
+    /// Drop the grabbed window at the previewed position
+    Drop,
+    /// Focus the previous window of the focus history
+    HistoryPrevious,
+    /// Focus the next window of the focus history
+    HistoryNext,
+    /// Show the rules applied to new windows
+    ShowRules,
+    ReloadRules,
+    /// Open the rules file in $EDITOR
+    EditRules,
+    /// Start typing an i3 command
+    OpenCommandLine,
+    CommandInput(char),
+    CommandBackspace,
+    /// Complete the word being typed, or cycle through the candidates
+    CommandComplete,
+    /// Recall an older command of the history
+    CommandOlder,
+    /// Recall a more recent command of the history
+    CommandNewer,
+    /// Send the typed command to i3
+    CommandRun,
+    /// Switch the Attached pane between the windows of the selected workspace and the windows
+    /// of every workspace grouped by class
+    ToggleClassView,
+    /// Move every window of the selected class to the selected workspace
+    GatherClass,
+    /// Close every window of the selected class
+    CloseClass,
+    /// Focus the next window of the selected class
+    CycleClass,
+    /// Write the tree as JSON, DOT and Markdown to the export directory
+    ExportTree,
+    /// Switch to the next order of the Workspaces pane
+    CycleWorkspaceSort,
+    /// Type the label of the selected workspace
+    EditWorkspaceLabel,
+    /// Type the note of the selected workspace
+    EditWorkspaceNote,
+    /// Save the label or note being typed
+    TextConfirm,
+    /// Rename the selected workspace to `num:label`
+    PushWorkspaceLabel,
+    /// Go back to the normal input mode
+    Cancel,
 }
 
-enum AppScreen {
+/// The screens of the TUI, switched with the number keys or tab
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum AppScreen {
     Home,
+    Tree,
+    Outputs,
+    Scratchpad,
+    Log,
+}
+
+impl AppScreen {
+    pub(crate) const ALL: [Self; 5] = [
+        AppScreen::Home,
+        AppScreen::Tree,
+        AppScreen::Outputs,
+        AppScreen::Scratchpad,
+        AppScreen::Log,
+    ];
+
+    pub(crate) fn title(&self) -> &'static str {
+        match self {
+            AppScreen::Home => "Home",
+            AppScreen::Tree => "Tree",
+            AppScreen::Outputs => "Outputs",
+            AppScreen::Scratchpad => "Scratchpad",
+            AppScreen::Log => "Log",
+        }
+    }
+
+    fn position(&self) -> usize {
+        AppScreen::ALL.iter().position(|s| s == self).unwrap_or(0)
+    }
+
+    /// Where toggling the log leads: the Log screen, or back to `before_log` from it
+    fn toggle_log(self, before_log: AppScreen) -> AppScreen {
+        match self {
+            AppScreen::Log => before_log,
+            _ => AppScreen::Log,
+        }
+    }
+
+    /// Label of the screen in the tab bar, e.g. `2 Tree`
+    fn tab_title(&self) -> String {
+        format!("{} {}", self.position() + 1, self.title())
+    }
 }
 
 use ratatui::layout::{Constraint, Direction, Layout};
 
+fn percentages(shares: &[u16]) -> Vec<Constraint> {
+    shares
+        .iter()
+        .map(|share| Constraint::Percentage(*share))
+        .collect()
+}
+
 fn home_layout(
     frame: &Frame,
+    sizes: &PaneSizes,
     selected: HomeLayoutSelectedWindow,
     workspace_index: u64,
     attached_windows_index: u64,
+    history_index: u64,
 ) -> HomeLayout {
     let home_layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints(vec![Constraint::Percentage(95), Constraint::Percentage(5)])
+        .constraints(vec![
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
         .split(frame.area());
+    let main = home_layout[1];
 
     let layout = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(home_layout[0]);
+        .constraints(percentages(&[sizes.left_width, 100 - sizes.left_width]))
+        .split(main);
 
     let right_layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+        .constraints(percentages(&sizes.right))
         .split(layout[1]);
 
     let left_layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .constraints(percentages(&sizes.left))
         .split(layout[0]);
 
+    let mut panes = [
+        left_layout[0],
+        left_layout[1],
+        left_layout[2],
+        right_layout[0],
+        right_layout[1],
+    ];
+    // A zoomed pane takes the whole screen, the others vanish
+    if sizes.zoomed {
+        let zoomed = match selected {
+            HomeLayoutSelectedWindow::Workspaces => 0,
+            HomeLayoutSelectedWindow::Focused => 1,
+            HomeLayoutSelectedWindow::History => 2,
+            HomeLayoutSelectedWindow::Attached => 3,
+            HomeLayoutSelectedWindow::Floating => 4,
+        };
+        for (index, pane) in panes.iter_mut().enumerate() {
+            *pane = if index == zoomed {
+                main
+            } else {
+                Rect::default()
+            };
+        }
+    }
+
+    let workspaces_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(panes[0]);
+
     HomeLayout {
-        attached_windows: right_layout[0],
-        floating_windows: right_layout[1],
-        focused_window: left_layout[1],
-        workspaces: left_layout[0],
-        status: home_layout[1],
+        attached_windows: panes[3],
+        floating_windows: panes[4],
+        focused_window: panes[1],
+        history: panes[2],
+        workspaces: workspaces_layout[0],
+        minimap: workspaces_layout[1],
+        tabs: home_layout[0],
+        main,
+        hints: home_layout[2],
+        status: home_layout[3],
         selected,
         workspaces_index: workspace_index,
         attached_windows_index: attached_windows_index,
+        history_index,
         workspace_state: None,
     }
 }
@@ -427,14 +943,394 @@ use ratatui::prelude::*;
 
 impl HomeLayout {
     fn render(&self, frame: &mut Frame, model: &mut Model) -> Result<()> {
-        // Render all the workspaces that exist.
-        self.render_workspace(frame, model);
-        self.render_focused(frame, model);
-        self.render_floating(frame, model);
-        self.render_attached(frame, model)?;
-        frame.render_widget(Text::raw(model.status_msg_display()), self.status);
+        frame.render_widget(
+            Tabs::new(AppScreen::ALL.iter().map(|screen| screen.tab_title()))
+                .select(model.screen.position())
+                .highlight_style(SELECTED_STYLE),
+            self.tabs,
+        );
+
+        match model.screen {
+            AppScreen::Home => {
+                // Render all the workspaces that exist.
+                self.render_workspace(frame, model);
+                self.render_minimap(frame, model);
+                self.render_focused(frame, model);
+                self.render_history(frame, model);
+                self.render_floating(frame, model);
+                self.render_attached(frame, model)?;
+            }
+            AppScreen::Tree => self.render_tree(frame, model),
+            AppScreen::Outputs => self.render_outputs(frame, model),
+            AppScreen::Scratchpad => self.render_scratchpad(frame, model),
+            AppScreen::Log => self.render_log(frame, self.main, model.screen_index),
+        }
+
+        let mode_prefix = match model.input_mode {
+            InputMode::Normal => "",
+            InputMode::Layout => "-- LAYOUT -- ",
+            InputMode::SwapPicker { .. } | InputMode::WorkspaceSwapPicker { .. } => "-- SWAP -- ",
+            InputMode::TemplatePicker { .. } => "-- TEMPLATE -- ",
+            InputMode::Grab { .. } => "-- GRAB -- ",
+            InputMode::Rules => "-- RULES -- ",
+            InputMode::Cleanup { .. } => "-- CLEANUP -- ",
+            InputMode::Confirm { .. } => "",
+            InputMode::ContextMenu { .. } => "-- MENU -- ",
+            InputMode::Help { .. } => "-- HELP -- ",
+            InputMode::Command { .. } | InputMode::Text { .. } => "",
+        };
+        self.render_hints(frame, model);
+        let status = match &model.input_mode {
+            InputMode::Command { input, .. } => {
+                let cursor = self.status.x + 1 + input.chars().count() as u16;
+                frame.set_cursor_position((cursor, self.status.y));
+                format!(":{}", input)
+            }
+            InputMode::Confirm { prompt, .. } => format!("{} (y/n)", prompt),
+            InputMode::Text { field, input } => {
+                let prompt = field.prompt();
+                let cursor = (prompt.chars().count() + input.chars().count()) as u16;
+                frame.set_cursor_position((self.status.x + cursor, self.status.y));
+                format!("{}{}", prompt, input)
+            }
+            _ => format!("{}{}", mode_prefix, model.status_msg_display()),
+        };
+        let status_style = match &model.status {
+            Some(entry) if entry.level == Level::Error => Style::new().red(),
+            _ => Style::new(),
+        };
+        frame.render_widget(Text::styled(status, status_style), self.status);
+
+        if let Some((title, items, index)) = model.picker() {
+            self.render_picker(frame, title, items, index);
+        }
+        if let InputMode::Rules = model.input_mode {
+            self.render_rules(frame, model);
+        }
+        if let InputMode::ContextMenu {
+            items,
+            index,
+            anchor,
+        } = &model.input_mode
+        {
+            self.render_context_menu(frame, items, *index, *anchor);
+        }
+        if let InputMode::Help { scroll } = model.input_mode {
+            self.render_help(frame, scroll);
+        }
         Ok(())
     }
+
+    /// Draw the geometry of the selected workspace's windows, scaled down to the pane
+    fn render_minimap(&self, frame: &mut Frame, model: &Model) {
+        let block = Block::bordered().title("Preview");
+        let Some(workspace) = model.ws_trees.get(&model.selected_workspace()) else {
+            frame.render_widget(block, self.minimap);
+            return;
+        };
+        let bounds = workspace.rect.unwrap_or_default();
+        let leaves = workspace.layout_leaves();
+
+        let canvas = Canvas::default()
+            .block(block)
+            .x_bounds([0.0, bounds.width as f64])
+            .y_bounds([0.0, bounds.height as f64])
+            .paint(|ctx| {
+                for (window, floating) in &leaves {
+                    let Some(rect) = window.rect else { continue };
+                    let color = if window.focused {
+                        GREEN.c400
+                    } else if *floating {
+                        BLUE.c400
+                    } else {
+                        SLATE.c400
+                    };
+
+                    // i3 has its origin in the top left corner whereas the canvas has it in
+                    // the bottom left one.
+                    let x = (rect.x - bounds.x) as f64;
+                    let y = bounds.height as f64 - (rect.y - bounds.y) as f64 - rect.height as f64;
+                    ctx.draw(&Rectangle {
+                        x,
+                        y,
+                        width: rect.width as f64,
+                        height: rect.height as f64,
+                        color,
+                    });
+                    ctx.print(
+                        x + rect.width as f64 * 0.05,
+                        y + rect.height as f64 * 0.5,
+                        Span::styled(window.class_name().unwrap_or_default(), color),
+                    );
+                    // Floating windows go on top of the tiled ones
+                    ctx.layer();
+                }
+            });
+
+        frame.render_widget(canvas, self.minimap);
+    }
+
+    /// Render a popup with the loaded rules and the log of the ones that matched
+    fn render_rules(&self, frame: &mut Frame, model: &Model) {
+        let area = centered_rect(80, 80, frame.area());
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(area);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            List::new(model.rules_snapshot.rules.iter().map(|rule| rule.summary())).block(
+                Block::bordered().title(format!(
+                    "Rules ({}) - r: reload, e: edit",
+                    config::config_file(RULES_FILE).display()
+                )),
+            ),
+            sections[0],
+        );
+        frame.render_widget(
+            List::new(
+                model
+                    .rules_snapshot
+                    .log
+                    .iter()
+                    .rev()
+                    .map(|entry| entry.log_str()),
+            )
+            .block(Block::bordered().title("Matches (most recent first)")),
+            sections[1],
+        );
+    }
+
+    /// One line with the bindings of the current mode, those of the selected pane first
+    fn render_hints(&self, frame: &mut Frame, model: &Model) {
+        // While cycling through completions, list them instead
+        if let InputMode::Command {
+            completion: Some(completion),
+            ..
+        } = &model.input_mode
+        {
+            let spans: Vec<Span> = completion
+                .candidates
+                .iter()
+                .enumerate()
+                .map(|(index, candidate)| {
+                    let span = Span::raw(format!("{}  ", candidate));
+                    if index == completion.index {
+                        span.style(SELECTED_STYLE)
+                    } else {
+                        span
+                    }
+                })
+                .collect();
+            frame.render_widget(Line::from(spans), self.hints);
+            return;
+        }
+
+        let mut bindings = keymap::for_mode(&model.input_mode, model.screen);
+        bindings.retain(|b| b.applies_to(self.selected));
+        bindings.sort_by_key(|b| b.panes.is_empty());
+
+        let mut spans = vec![];
+        for binding in bindings {
+            spans.push(Span::styled(
+                binding.keys_str(),
+                Style::new().fg(YELLOW.c300).add_modifier(Modifier::BOLD),
+            ));
+            spans.push(Span::raw(format!(" {}  ", binding.description)));
+        }
+        frame.render_widget(Line::from(spans), self.hints);
+    }
+
+    /// The most recent log entries, colored by level
+    fn render_log(&self, frame: &mut Frame, area: Rect, scroll: usize) {
+        let count = area.height.saturating_sub(2) as usize;
+
+        let lines: Vec<Line> = log::tail(count, scroll)
+            .iter()
+            .map(|entry| {
+                let style = match entry.level {
+                    Level::Debug => Style::new().fg(SLATE.c500),
+                    Level::Info => Style::new(),
+                    Level::Warning => Style::new().fg(YELLOW.c300),
+                    Level::Error => Style::new().red(),
+                };
+                Line::styled(
+                    format!(
+                        "{} {:5} {}",
+                        entry.clock(),
+                        entry.level.as_str(),
+                        entry.message
+                    ),
+                    style,
+                )
+            })
+            .collect();
+
+        let title = match scroll {
+            0 => format!("Log ({} entries)", log::len()),
+            _ => format!("Log ({} entries, {} newer hidden)", log::len(), scroll),
+        };
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+
+    /// Every container of every workspace, indented by depth
+    fn render_tree(&self, frame: &mut Frame, model: &Model) {
+        frame.render_widget(
+            Paragraph::new(model.tree_lines().join("\n"))
+                .block(Block::bordered().title("i3 tree"))
+                .scroll((model.screen_index as u16, 0)),
+            self.main,
+        );
+    }
+
+    fn render_outputs(&self, frame: &mut Frame, model: &Model) {
+        frame.render_widget(
+            Paragraph::new(
+                model
+                    .outputs
+                    .iter()
+                    .map(|output| output.output_str())
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            )
+            .block(Block::bordered().title("Outputs"))
+            .scroll((model.screen_index as u16, 0)),
+            self.main,
+        );
+    }
+
+    fn render_scratchpad(&self, frame: &mut Frame, model: &Model) {
+        let mut state = ListState::default();
+        state.select(Some(model.screen_index));
+
+        frame.render_stateful_widget(
+            List::new(model.scratchpad.iter().map(|window| window.name_str()))
+                .block(Block::bordered().title("Scratchpad"))
+                .highlight_style(SELECTED_STYLE)
+                .highlight_symbol("> "),
+            self.main,
+            &mut state,
+        );
+    }
+
+    /// Every binding of the keymap, grouped by pane and mode
+    fn render_help(&self, frame: &mut Frame, scroll: u16) {
+        let area = centered_rect(60, 80, frame.area());
+        let key_style = Style::new().fg(YELLOW.c300).add_modifier(Modifier::BOLD);
+
+        let mut lines = vec![];
+        for (section, bindings) in keymap::help_sections() {
+            lines.push(Line::styled(
+                section,
+                Style::new().add_modifier(Modifier::UNDERLINED),
+            ));
+            for binding in bindings {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:<12}", binding.keys_str()), key_style),
+                    Span::raw(binding.description),
+                ]));
+            }
+            lines.push(Line::default());
+        }
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title("Help (j/k to scroll, ? to close)"))
+                .scroll((scroll, 0)),
+            area,
+        );
+    }
+
+    /// Render the menu of a right click, next to the cell at `anchor`
+    fn render_context_menu(
+        &self,
+        frame: &mut Frame,
+        items: &[(String, Message)],
+        index: usize,
+        anchor: (u16, u16),
+    ) {
+        let area = context_menu_rect(anchor, items, frame.area());
+        let mut state = ListState::default();
+        state.select(Some(index));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(
+            List::new(items.iter().map(|(label, _)| label.clone()))
+                .block(Block::bordered())
+                .highlight_style(SELECTED_STYLE),
+            area,
+            &mut state,
+        );
+    }
+
+    /// The pane under a given terminal cell
+    fn pane_at(&self, column: u16, row: u16) -> Option<HomeLayoutSelectedWindow> {
+        [
+            (self.workspaces, HomeLayoutSelectedWindow::Workspaces),
+            (self.minimap, HomeLayoutSelectedWindow::Workspaces),
+            (self.focused_window, HomeLayoutSelectedWindow::Focused),
+            (self.history, HomeLayoutSelectedWindow::History),
+            (self.attached_windows, HomeLayoutSelectedWindow::Attached),
+            (self.floating_windows, HomeLayoutSelectedWindow::Floating),
+        ]
+        .into_iter()
+        .find(|(rect, _)| rect_contains(*rect, column, row))
+        .map(|(_, pane)| pane)
+    }
+
+    /// Render a popup list to pick an item from
+    fn render_picker(&self, frame: &mut Frame, title: String, items: Vec<String>, index: usize) {
+        let area = centered_rect(60, 60, frame.area());
+        let mut state = ListState::default();
+        state.select(Some(index));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(SELECTED_STYLE)
+                .highlight_symbol("> "),
+            area,
+            &mut state,
+        );
+    }
+}
+
+/// Carve a rectangle of the given percentages out of the center of `r`
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// Ask the daemon for its copy of the tree, querying i3 ourselves when it isn't running.
+fn load_snapshot() -> Result<Snapshot> {
+    daemon::snapshot().or_else(|_| Snapshot::collect())
+}
+
+/// Like [`load_snapshot`], but up to date even right after we changed the tree ourselves.
+fn fresh_snapshot() -> Result<Snapshot> {
+    daemon::refresh().or_else(|_| Snapshot::collect())
 }
 
 const SELECTED_STYLE: style::Style = style::Style::new()
@@ -443,111 +1339,317 @@ const SELECTED_STYLE: style::Style = style::Style::new()
 
 impl Model {
     /// Initialize a new Model.
-    pub(crate) fn new(frame: &Frame) -> Self {
-        let windows = list_windows();
-        let workspaces = list_workspaces();
-        let ws_map = list_workspaces_and_windows();
-        let ws_map_names = list_workspaces_and_window_names();
-        let fcsd_window = get_focused_window();
-        let floating_windows = list_floating_windows();
-        let attached_windows = list_attached_windows();
-        let startup_time = SystemTime::now();
-
-        Model {
+    pub(crate) fn new(frame: &Frame, settings: Settings) -> Self {
+        let (snapshot, load_error) = match load_snapshot() {
+            Ok(snapshot) => (snapshot, None),
+            Err(err) => (Snapshot::default(), Some(err)),
+        };
+        let Snapshot {
+            windows,
+            attached_windows,
+            floating_windows,
+            workspaces,
+            ws_map,
+            ws_map_names,
+            ws_trees,
+            focused: fcsd_window,
+            docks,
+        } = snapshot;
+        let mut process_monitor = ProcessMonitor::new();
+        process_monitor.refresh(&windows);
+
+        // Keep track of the focus history while the TUI is open
+        let mut history = History::load();
+        if let Some(focused) = &fcsd_window {
+            history.record(focused);
+        }
+        let own_window = fcsd_window.as_ref().map(|window| window.id);
+        let origin = Origin::capture(&workspaces, &history, own_window);
+        let window_events = events::subscribe::<WindowEvent>(&["window"]).ok();
+        let rules = RulesEngine::load();
+        let pane_sizes = PaneSizes::load();
+
+        let mut model = Model {
             windows,
             workspaces,
+            workspace_sort: WorkspaceSort::load(),
+            workspace_notes: WorkspaceNotes::load(),
             fcsd_window,
             ws_map,
             ws_map_names,
+            ws_trees,
             running_state: RunningState::Running,
             attached_windows,
             floating_windows,
-            status_msg: "Initialized Application".to_string(),
-            status_timestamp: startup_time,
-            startup_time,
+            docks,
+            status: None,
             current_menu: AppLayout::HomeLayout,
             home_layout: Some(home_layout(
                 frame,
+                &pane_sizes,
                 HomeLayoutSelectedWindow::Workspaces,
                 0,
                 0,
+                0,
             )),
+            process_monitor,
+            input_mode: InputMode::Normal,
+            history,
+            window_events,
+            rules_snapshot: rules.snapshot(),
+            rules,
+            pending_edit: None,
+            command_history: CommandHistory::load(),
+            screen: AppScreen::Home,
+            screen_before_log: AppScreen::Home,
+            screen_index: 0,
+            pane_sizes,
+            outputs: vec![],
+            scratchpad: vec![],
+            class_view: false,
+            class_groups: vec![],
+            collapsed_classes: HashSet::new(),
+            class_view_index: 0,
+            settings,
+            own_window,
+            origin,
+            frame_area: frame.area(),
+            last_click: None,
+        };
+        model.sort_workspaces();
+        match load_error {
+            Some(err) => model.report_error(err),
+            None => model.update_status("Initialized Application"),
         }
+        model
     }
 
     fn workspace_windows(&self, workspace_name: &str) -> Result<Vec<Window>> {
-        let cloned_map = self.ws_map.clone();
-        let nodes = cloned_map.get(workspace_name).unwrap();
-
-        Ok(nodes
-            .iter()
+        Ok(self
+            .ws_map
+            .get(workspace_name)
+            .into_iter()
+            .flatten()
             .flat_map(|window| window.flatten())
-            .filter(|ws| ws.name.is_some())
+            .filter(|w| w.is_window())
             .collect())
     }
 
-    /// Send a focus command to get back to this window
-    fn focus(&self) -> Result<String> {
-        let focused = self.fcsd_window.clone().unwrap();
-        i3_cmd(&[&format!(r#"[con_id="{}"]"#, focused.id), "focus"])
-    }
-
     /// Retrieve all of the windows that belong to a given workspace
     fn workspace_window_names(&self, workspace_name: &str) -> Result<Vec<String>> {
         Ok(self
             .workspace_windows(workspace_name)?
             .iter()
-            .map(|ws| ws.name_str())
+            .map(|ws| self.window_str(ws))
             .collect())
     }
 
-    /// Retrieve the name of the selected workspace
+    /// Display a window along with the resources used by its process
+    fn window_str(&self, window: &Window) -> String {
+        match self.process_monitor.usage(window) {
+            Some(usage) => format!("{} {}", window.name_str(), usage.usage_str()),
+            None => window.name_str(),
+        }
+    }
+
+    /// Retrieve the name of the selected workspace, empty when there are no workspaces
     fn selected_workspace(&self) -> String {
+        let index = self.home_layout.as_ref().unwrap().workspaces_index as usize;
         self.workspaces
-            .get(self.home_layout.as_ref().unwrap().workspaces_index as usize)
-            .unwrap_or(&self.workspaces[0])
-            .name
-            .clone()
+            .get(index)
+            .or(self.workspaces.first())
+            .map(|workspace| workspace.name.clone())
+            .unwrap_or_default()
     }
 
-    fn selected_attached_window(&self) -> Window {
+    fn selected_attached_window(&self) -> Option<Window> {
         let selected_ws = self.selected_workspace();
         let nodes = self.workspace_windows(&selected_ws).unwrap();
         nodes
             .get(self.hl().attached_windows_index as usize)
-            .unwrap_or(&nodes[0])
-            .clone()
+            .cloned()
     }
 
     fn delete_attached_window(&mut self) -> Result<()> {
-        let selected_window = self.selected_attached_window();
-        selected_window.focus_window()?;
+        if let Some(selected_window) = self.selected_attached_window() {
+            selected_window.focus_window()?;
+        }
         Ok(())
     }
 
-    /// Update the display value of the update status string
-    pub(crate) fn update_status(&mut self, new_status: &str) {
-        self.status_timestamp = SystemTime::now();
-        self.status_msg = new_status.to_string();
+    /// Order the workspaces according to the chosen sort mode
+    fn sort_workspaces(&mut self) {
+        let mut counts = HashMap::new();
+        let mut owners = HashMap::new();
+        for workspace in &self.workspaces {
+            let windows = self.workspace_windows(&workspace.name).unwrap();
+            counts.insert(workspace.name.clone(), windows.len());
+            for window in windows {
+                owners.insert(window.id, workspace.name.clone());
+            }
+        }
+
+        // The rank of the most recently focused window of each workspace
+        let mut last_focused = HashMap::new();
+        for workspace in self.workspaces.iter().filter(|ws| ws.focused) {
+            last_focused.insert(workspace.name.clone(), 0);
+        }
+        for (rank, entry) in self.history.entries().iter().enumerate() {
+            if let Some(name) = owners.get(&entry.id) {
+                last_focused.entry(name.clone()).or_insert(rank + 1);
+            }
+        }
+
+        workspace::sort_workspaces(
+            &mut self.workspaces,
+            self.workspace_sort,
+            |name| counts.get(name).copied().unwrap_or(0),
+            |name| last_focused.get(name).copied(),
+        );
+
+        // Grouped in the order of the workspaces
+        self.class_groups =
+            groups::group_by_class(ordered_trees(&self.workspaces, &self.ws_trees).into_iter());
     }
 
-    /// Get the time elapsed from application startup in a human readable format
-    fn elapsed_time_string(&self) -> String {
-        let elapsed_time = self
-            .status_timestamp
-            .duration_since(self.startup_time)
-            .unwrap();
+    fn save_workspace_note(&mut self) {
+        let InputMode::Text { field, input } = self.input_mode.clone() else {
+            return;
+        };
+        self.input_mode = InputMode::Normal;
 
-        format!(
-            "{:3}.{:03}",
-            elapsed_time.as_secs() % 1000,
-            elapsed_time.as_millis() % 1000
-        )
+        let input = input.trim().to_string();
+        let workspace = match &field {
+            TextField::Label(workspace) | TextField::Note(workspace) => workspace.clone(),
+        };
+        self.workspace_notes.update(&workspace, |note| match field {
+            TextField::Label(_) => note.label = input,
+            TextField::Note(_) => note.note = input,
+        });
+        match self.workspace_notes.save() {
+            Ok(()) => self.update_status(&format!("Updated the notes of {}", workspace)),
+            Err(err) => self.report_error(err),
+        }
     }
 
-    /// Display the status message along with a timestamp.
-    fn status_msg_display(&self) -> String {
-        format!("[{}] {}", self.elapsed_time_string(), self.status_msg)
+    /// Rename the selected workspace after its label, so that i3bar shows it
+    fn push_workspace_label(&mut self) -> Result<()> {
+        let index = self.hl().workspaces_index as usize;
+        let Some(workspace) = self.workspaces.get(index).cloned() else {
+            return Ok(());
+        };
+        let label = self
+            .workspace_notes
+            .get(&workspace.name)
+            .map(|note| note.label.clone())
+            .unwrap_or_default();
+        let name = labelled_name(&workspace, &label);
+        if name.is_empty() || name == workspace.name {
+            return Ok(());
+        }
+
+        let reply = i3_cmd(&[
+            "rename",
+            "workspace",
+            &quoted(&workspace.name),
+            "to",
+            &quoted(&name),
+        ])?;
+        palette::summarize_reply(&reply)?;
+        self.workspace_notes.rename(&workspace.name, &name);
+        self.workspace_notes.save()?;
+        self.refresh();
+        self.update_status(&format!("Renamed workspace {} to {}", workspace.name, name));
+        Ok(())
+    }
+
+    fn cycle_workspace_sort(&mut self) {
+        let selection = self.selection();
+        self.workspace_sort = self.workspace_sort.next();
+        self.sort_workspaces();
+        self.restore_selection(selection);
+        if let Err(err) = self.workspace_sort.save() {
+            self.report_error(err);
+        } else {
+            self.update_status(&format!(
+                "Sorting workspaces by {}",
+                self.workspace_sort.label()
+            ));
+        }
+    }
+
+    /// What the cursors of the home screen point at
+    fn selection(&self) -> Selection {
+        let hl = self.hl();
+        Selection {
+            workspace: self
+                .workspaces
+                .get(hl.workspaces_index as usize)
+                .map(|workspace| workspace.id),
+            window: self.selected_attached_window().map(|window| window.id),
+            history: self
+                .history
+                .entries()
+                .get(hl.history_index as usize)
+                .map(|entry| entry.id),
+        }
+    }
+
+    /// Put the cursors back on the items they pointed at before the tree changed, or on their
+    /// closest neighbour when these items are gone.
+    fn restore_selection(&mut self, selection: Selection) {
+        let workspace_ids: Vec<u64> = self.workspaces.iter().map(|w| w.id).collect();
+        let history_ids: Vec<u64> = self.history.entries().iter().map(|e| e.id).collect();
+        let hl = self.hl();
+        let workspaces_index = follow(&workspace_ids, selection.workspace, hl.workspaces_index);
+        self.hl_mut().workspaces_index = workspaces_index;
+
+        let window_ids: Vec<u64> = self
+            .workspace_windows(&self.selected_workspace())
+            .unwrap()
+            .iter()
+            .map(|w| w.id)
+            .collect();
+        // Another workspace got selected when the previous one vanished
+        let same_workspace = selection.workspace.is_some()
+            && workspace_ids.get(workspaces_index as usize) == selection.workspace.as_ref();
+        let attached_windows_index = if same_workspace {
+            follow(&window_ids, selection.window, hl.attached_windows_index)
+        } else {
+            0
+        };
+
+        let n_class_rows = groups::rows(&self.class_groups, &self.collapsed_classes).len();
+        self.class_view_index = self
+            .class_view_index
+            .min(n_class_rows.saturating_sub(1) as u64);
+
+        let hl = self.hl_mut();
+        hl.attached_windows_index = attached_windows_index;
+        hl.history_index = follow(&history_ids, selection.history, hl.history_index);
+    }
+
+    /// Update the display value of the update status string
+    pub(crate) fn update_status(&mut self, new_status: &str) {
+        self.set_status(Level::Info, new_status);
+    }
+
+    pub(crate) fn report_error(&mut self, err: impl std::fmt::Display) {
+        self.set_status(Level::Error, &format!("Error: {}", err));
+    }
+
+    /// Show a message in the status bar and record it in the log
+    fn set_status(&mut self, level: Level, message: &str) {
+        log::record(level, message);
+        self.status = log::last();
+    }
+
+    /// Display the status message along with the time it was set at.
+    fn status_msg_display(&self) -> String {
+        match &self.status {
+            Some(entry) => format!("[{}] {}", entry.clock(), entry.message),
+            None => String::new(),
+        }
     }
 
     pub(crate) fn view(&mut self, frame: &mut Frame) -> Result<()> {
@@ -556,11 +1658,17 @@ impl Model {
                 Some(h_layout) => {
                     let layout = home_layout(
                         frame,
+                        &self.pane_sizes,
                         h_layout.selected,
                         h_layout.workspaces_index,
                         h_layout.attached_windows_index,
+                        h_layout.history_index,
                     );
                     let _ = layout.render(frame, self);
+
+                    // Keep the areas we just drew around to find what gets clicked
+                    self.home_layout = Some(layout);
+                    self.frame_area = frame.area();
                 }
                 None => (),
             },
@@ -570,39 +1678,281 @@ impl Model {
     }
 
     pub(crate) fn handle_event(&mut self) -> Result<Option<Message>> {
+        self.process_window_events();
+
         if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == event::KeyEventKind::Press {
+            match event::read()? {
+                Event::Key(key) if key.kind == event::KeyEventKind::Press => {
                     return Ok(self.handle_key(key));
                 }
+                Event::Mouse(mouse) => return Ok(self.handle_mouse(mouse)),
+                _ => (),
             }
         }
         Ok(None)
     }
 
-    pub(crate) fn handle_key(&mut self, key: event::KeyEvent) -> Option<Message> {
-        match key.code {
-            // KeyCode::Char('j') => Some(Message::Increment),
-            // KeyCode::Char('k') => Some(Message::Decrement),
-            KeyCode::Char('q') => Some(Message::Quit),
-            KeyCode::Char('s') => Some(Message::ShowKey),
-            KeyCode::Char('r') => Some(Message::RefreshModel),
-            KeyCode::Char('H') => Some(Message::MoveLeftMenu),
-            KeyCode::Char('J') => Some(Message::MoveDownMenu),
-            KeyCode::Char('K') => Some(Message::MoveUpMenu),
-            KeyCode::Char('L') => Some(Message::MoveRightMenu),
-            KeyCode::Char('h') => Some(Message::MoveLeft),
-            KeyCode::Char('j') => Some(Message::MoveDown),
-            KeyCode::Char('k') => Some(Message::MoveUp),
-            KeyCode::Char('l') => Some(Message::MoveRight),
-            KeyCode::Char('a') => Some(Message::Add),
-            KeyCode::Char('c') => Some(Message::Consolidate),
-            KeyCode::Char('d') => Some(Message::Delete),
-            KeyCode::Enter => Some(Message::GoTo),
+    /// Map mouse events to messages, selecting the pane and item under the cursor on the way
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> Option<Message> {
+        let (column, row) = (mouse.column, mouse.row);
+
+        // An open context menu captures all the clicks
+        if let InputMode::ContextMenu {
+            items,
+            index,
+            anchor,
+        } = &mut self.input_mode
+        {
+            if let MouseEventKind::Down(MouseButton::Left) = mouse.kind {
+                let area = context_menu_rect(*anchor, items, self.frame_area);
+                let item = row.saturating_sub(area.y + 1) as usize;
+                if rect_contains(area, column, row) && row > area.y && item < items.len() {
+                    *index = item;
+                    return Some(Message::PickerConfirm);
+                }
+                return Some(Message::Cancel);
+            }
+            return None;
+        }
+        // The other modes are driven by the keyboard only
+        if !matches!(self.input_mode, InputMode::Normal) {
+            return None;
+        }
+
+        let hl = self.hl();
+        if let MouseEventKind::Down(MouseButton::Left) = mouse.kind {
+            if rect_contains(hl.tabs, column, row) {
+                return tab_at(column - hl.tabs.x).map(Message::SwitchScreen);
+            }
+        }
+        // Only the home screen has panes to click on
+        if self.screen != AppScreen::Home {
+            return match mouse.kind {
+                MouseEventKind::ScrollDown => Some(Message::MoveDown),
+                MouseEventKind::ScrollUp => Some(Message::MoveUp),
+                _ => None,
+            };
+        }
+
+        let pane = self.hl().pane_at(column, row)?;
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let double_click = self.register_click(column, row);
+                self.select_at(pane, column, row);
+                double_click.then_some(Message::GoTo)
+            }
+            MouseEventKind::Down(MouseButton::Right) => {
+                self.select_at(pane, column, row);
+                let items = self.context_actions(pane);
+                if !items.is_empty() {
+                    self.input_mode = InputMode::ContextMenu {
+                        items,
+                        index: 0,
+                        anchor: (column, row),
+                    };
+                }
+                None
+            }
+            MouseEventKind::ScrollDown => {
+                self.hl_mut().selected = pane;
+                Some(Message::MoveDown)
+            }
+            MouseEventKind::ScrollUp => {
+                self.hl_mut().selected = pane;
+                Some(Message::MoveUp)
+            }
             _ => None,
         }
     }
 
+    /// Remember a click, returning true when it completes a double-click
+    fn register_click(&mut self, column: u16, row: u16) -> bool {
+        let now = Instant::now();
+        let double_click = matches!(
+            self.last_click,
+            Some((time, c, r)) if c == column && r == row && now.duration_since(time) < DOUBLE_CLICK_DELAY
+        );
+        self.last_click = if double_click {
+            None
+        } else {
+            Some((now, column, row))
+        };
+        double_click
+    }
+
+    /// Select a pane, and the list item at `row` if the click landed on one
+    fn select_at(&mut self, pane: HomeLayoutSelectedWindow, column: u16, row: u16) {
+        let n_attached = self.n_attached_windows();
+        let n_history = self.history.entries().len();
+        let n_workspaces = self.workspaces.len();
+        let n_class_rows = groups::rows(&self.class_groups, &self.collapsed_classes).len();
+        let hl = self.home_layout.as_mut().unwrap();
+        hl.selected = pane;
+
+        let (rect, index, n_items) = match pane {
+            HomeLayoutSelectedWindow::Attached if self.class_view => (
+                hl.attached_windows,
+                &mut self.class_view_index,
+                n_class_rows,
+            ),
+            HomeLayoutSelectedWindow::Workspaces => {
+                (hl.workspaces, &mut hl.workspaces_index, n_workspaces)
+            }
+            HomeLayoutSelectedWindow::Attached => (
+                hl.attached_windows,
+                &mut hl.attached_windows_index,
+                n_attached,
+            ),
+            HomeLayoutSelectedWindow::History => (hl.history, &mut hl.history_index, n_history),
+            _ => return,
+        };
+
+        // Skip the borders
+        if !rect_contains(rect, column, row) || row == rect.y || row + 1 >= rect.y + rect.height {
+            return;
+        }
+
+        // Lists are scrolled just enough for the selected item to be visible
+        let inner_height = rect.height.saturating_sub(2) as u64;
+        let offset = (*index + 1).saturating_sub(inner_height);
+        let item = offset + (row - rect.y - 1) as u64;
+        if (item as usize) < n_items {
+            *index = item;
+        }
+    }
+
+    /// Actions offered by the context menu of a pane
+    fn context_actions(&self, pane: HomeLayoutSelectedWindow) -> Vec<(String, Message)> {
+        let actions = match pane {
+            HomeLayoutSelectedWindow::Workspaces => vec![
+                ("Go to workspace", Message::GoTo),
+                ("Add workspace", Message::Add),
+                ("Consolidate windows here", Message::Consolidate),
+                ("Swap windows with...", Message::OpenWorkspaceSwapPicker),
+                ("Screenshot all windows", Message::Screenshot),
+                ("Apply template", Message::OpenTemplatePicker),
+            ],
+            HomeLayoutSelectedWindow::Attached if self.class_view => vec![
+                ("Focus (or fold group)", Message::GoTo),
+                ("Gather class here", Message::GatherClass),
+                ("Close class", Message::CloseClass),
+                ("Cycle focus in class", Message::CycleClass),
+                ("Close", Message::Delete),
+                ("Layout mode", Message::EnterLayoutMode),
+                ("Ungroup", Message::ToggleClassView),
+            ],
+            HomeLayoutSelectedWindow::Attached => vec![
+                ("Focus", Message::GoTo),
+                ("Close", Message::Delete),
+                ("Kill client", Message::Kill(KillMethod::KillClient)),
+                ("Send SIGTERM", Message::Kill(KillMethod::Terminate)),
+                ("Send SIGKILL", Message::Kill(KillMethod::ForceKill)),
+                ("Layout mode", Message::EnterLayoutMode),
+                ("Swap with...", Message::OpenSwapPicker),
+                ("Grab", Message::StartGrab),
+                ("Screenshot", Message::Screenshot),
+            ],
+            HomeLayoutSelectedWindow::Focused => vec![
+                ("Layout mode", Message::EnterLayoutMode),
+                ("Swap with...", Message::OpenSwapPicker),
+                ("Screenshot", Message::Screenshot),
+            ],
+            HomeLayoutSelectedWindow::History => vec![("Focus", Message::GoTo)],
+            HomeLayoutSelectedWindow::Floating => vec![("Refresh", Message::RefreshModel)],
+        };
+
+        actions
+            .into_iter()
+            .map(|(label, message)| (label.to_string(), message))
+            .collect()
+    }
+
+    /// Feed the focus events received since the last call into the history and apply the
+    /// rules to new windows, unless the daemon already takes care of them.
+    fn process_window_events(&mut self) {
+        let Some(receiver) = &self.window_events else {
+            return;
+        };
+        let events: Vec<WindowEvent> = receiver.try_iter().collect();
+
+        let mut changed = false;
+        let mut new_windows = false;
+        // The daemon applies the rules itself when it runs
+        let apply_rules = !events.is_empty() && !daemon::is_running();
+        for event in &events {
+            match event.change.as_str() {
+                "focus" => {
+                    // Back in the TUI, e.g. the popup got shown again: remember where from
+                    if Some(event.container.id) == self.own_window {
+                        self.origin =
+                            Origin::capture(&list_workspaces(), &self.history, self.own_window);
+                    }
+                    changed |= self.history.record(&event.container);
+                }
+                "new" => {
+                    new_windows = true;
+                    if apply_rules {
+                        self.rules.handle_new_window(&event.container);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if changed {
+            if let Err(err) = self.history.save() {
+                let message = format!("Unable to save the history: {}", err);
+                self.set_status(Level::Warning, &message);
+            }
+        }
+        if new_windows {
+            self.sync_rules(false);
+        }
+    }
+
+    /// Update the rules displayed in the Rules pane, from the daemon when it is running
+    fn sync_rules(&mut self, reload: bool) {
+        let action = if reload { "reload" } else { "get" };
+        match daemon::request("rules", serde_json::json!({ "action": action })) {
+            Ok(snapshot) => {
+                if let Ok(snapshot) = serde_json::from_value::<RulesSnapshot>(snapshot) {
+                    self.rules_snapshot = snapshot;
+                }
+            }
+            Err(_) => {
+                if reload {
+                    self.rules.reload();
+                }
+                self.rules_snapshot = self.rules.snapshot();
+            }
+        }
+    }
+
+    /// Path of a file the user asked to edit, to be opened by the main loop once the TUI is
+    /// suspended.
+    pub(crate) fn take_pending_edit(&mut self) -> Option<PathBuf> {
+        self.pending_edit.take()
+    }
+
+    pub(crate) fn handle_key(&mut self, key: event::KeyEvent) -> Option<Message> {
+        let pane = self.home_layout.as_ref().map(|hl| hl.selected);
+        let message = keymap::lookup(
+            &keymap::for_mode(&self.input_mode, self.screen),
+            key.code,
+            pane,
+        );
+        match self.input_mode {
+            // Any key that isn't a layout command leaves layout mode, and any key but `y`
+            // declines a confirmation
+            InputMode::Layout | InputMode::Confirm { .. } => message.or(Some(Message::Cancel)),
+            InputMode::Command { .. } | InputMode::Text { .. } => message.or(match key.code {
+                KeyCode::Char(c) => Some(Message::CommandInput(c)),
+                _ => None,
+            }),
+            _ => message,
+        }
+    }
+
     /// Delete a window or workspace
     fn handle_delete(&mut self) -> Result<()> {
         let hl = self.home_layout.clone().unwrap();
@@ -612,17 +1962,43 @@ impl Model {
                 //
                 ()
             }
-            HomeLayoutSelectedWindow::Attached => {
-                let selected_window = self.selected_attached_window();
-                selected_window.kill()?;
-                self.refresh();
-                self.update_status(&format!("Killed: {:?}", selected_window));
-            }
+            HomeLayoutSelectedWindow::Attached => self.handle_kill(KillMethod::Close)?,
             _ => (),
         }
         Ok(())
     }
 
+    /// Get rid of the selected window, either politely or by signaling its process
+    fn handle_kill(&mut self, method: KillMethod) -> Result<()> {
+        let hl = self.home_layout.clone().unwrap();
+        if let HomeLayoutSelectedWindow::Attached = hl.selected {
+            let Some(selected_window) = self.selected_target_window() else {
+                return Ok(());
+            };
+            selected_window.kill_with(method)?;
+            self.refresh();
+            self.update_status(&format!(
+                "{}: {}",
+                method.describe(),
+                selected_window.name_str()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get the TUI out of the way once an action took the focus elsewhere
+    fn finish_action(&mut self, after: PostAction) -> Result<()> {
+        match after {
+            PostAction::Stay => (),
+            PostAction::Hide if popup::is_popup(self.own_window) => {
+                popup::hide()?;
+            }
+            PostAction::Hide => (),
+            PostAction::Quit => self.running_state = RunningState::Done,
+        }
+        Ok(())
+    }
+
     /// Jump to a specific window or workspace
     fn handle_goto(&mut self) -> Result<()> {
         let hl = self.home_layout.clone().unwrap();
@@ -630,14 +2006,22 @@ impl Model {
             HomeLayoutSelectedWindow::Workspaces => {
                 // Jump to the selected workspace
                 let selected = self.selected_workspace();
-                shutils::i3_cmd(&["workspace", &selected])?;
-                hl.show_scratchpad()?;
+                shutils::i3_cmd(&["workspace", &quoted(&selected)])?;
+                self.finish_action(self.settings.after.goto)?;
             }
             HomeLayoutSelectedWindow::Attached => {
-                let selected_window = self.selected_attached_window();
-                selected_window.focus_window()?;
-                self.hl().show_scratchpad()?;
-                self.update_status(&format!("Focused: {:?}", selected_window));
+                if let Some(selected_window) = self.selected_attached_window() {
+                    selected_window.focus_window()?;
+                    self.update_status(&format!("Focused: {}", selected_window.name_str()));
+                    self.finish_action(self.settings.after.goto)?;
+                }
+            }
+            HomeLayoutSelectedWindow::History => {
+                if let Some(entry) = self.history.entries().get(hl.history_index as usize) {
+                    entry.focus()?;
+                    self.update_status(&format!("Focused: {}", entry.name_str()));
+                    self.finish_action(self.settings.after.goto)?;
+                }
             }
 
             _ => (),
@@ -645,6 +2029,370 @@ impl Model {
         Ok(())
     }
 
+    /// The window that layout commands apply to: the one selected in the Attached pane or the
+    /// focused window.
+    fn selected_target_window(&self) -> Option<Window> {
+        match self.hl().selected {
+            HomeLayoutSelectedWindow::Attached if self.class_view => self
+                .selected_class_row()
+                .and_then(|(group, row)| match row {
+                    GroupRow::Window(_, window) => Some(group.windows[window].window.clone()),
+                    GroupRow::Group(_) => None,
+                }),
+            HomeLayoutSelectedWindow::Attached => self.selected_attached_window(),
+            HomeLayoutSelectedWindow::Focused => self.fcsd_window.clone(),
+            _ => None,
+        }
+    }
+
+    /// Windows that `source` can be swapped with
+    fn swap_candidates(&self, source: &Window) -> Vec<Window> {
+        self.windows
+            .iter()
+            .filter(|w| w.is_window() && w.id != source.id)
+            .cloned()
+            .collect()
+    }
+
+    fn apply_container_command(&mut self, command: ContainerCommand) -> Result<()> {
+        match self.selected_target_window() {
+            Some(window) => {
+                window.apply(&command)?;
+                self.refresh();
+                self.update_status(&format!(
+                    "{}: {}",
+                    command.command(),
+                    window.name.unwrap_or_default()
+                ));
+            }
+            None => self.update_status("No window selected"),
+        }
+        Ok(())
+    }
+
+    /// Title, items and selected index of the picker being displayed, if any
+    fn picker(&self) -> Option<(String, Vec<String>, usize)> {
+        match &self.input_mode {
+            InputMode::SwapPicker { source, index } => Some((
+                format!("Swap {} with", source.name.clone().unwrap_or_default()),
+                self.swap_candidates(source)
+                    .iter()
+                    .map(|window| window.name_str())
+                    .collect(),
+                *index,
+            )),
+            InputMode::WorkspaceSwapPicker { source, index } => Some((
+                format!("Swap the windows of {} with", source),
+                self.workspace_swap_candidates(source),
+                *index,
+            )),
+            InputMode::Cleanup {
+                findings,
+                checked,
+                index,
+                target,
+            } => Some((
+                format!("Cleanup - Space: check, d: close, m: move to {}", target),
+                findings
+                    .iter()
+                    .zip(checked)
+                    .map(|(finding, checked)| {
+                        let check = if *checked { "[x]" } else { "[ ]" };
+                        format!("{} {}", check, finding.finding_str())
+                    })
+                    .collect(),
+                *index,
+            )),
+            InputMode::TemplatePicker { names, index } => Some((
+                format!(
+                    "Apply template ({})",
+                    config::config_file(TEMPLATES_FILE).display()
+                ),
+                names.clone(),
+                *index,
+            )),
+            _ => None,
+        }
+    }
+
+    fn handle_template_confirm(&mut self) -> Result<()> {
+        if let InputMode::TemplatePicker { names, index } = self.input_mode.clone() {
+            self.input_mode = InputMode::Normal;
+            if let Some(name) = names.get(index) {
+                apply_template(name)?;
+                self.refresh();
+                self.update_status(&format!("Applied template: {}", name));
+                self.finish_action(self.settings.after.template)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_swap_confirm(&mut self) -> Result<()> {
+        if let InputMode::SwapPicker { source, index } = self.input_mode.clone() {
+            self.input_mode = InputMode::Normal;
+            if let Some(target) = self.swap_candidates(&source).get(index) {
+                source.apply(&ContainerCommand::SwapWith(target.id))?;
+                self.refresh();
+                self.update_status(&format!(
+                    "Swapped {} with {}",
+                    source.name.unwrap_or_default(),
+                    target.name.clone().unwrap_or_default()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Workspaces that `source` can exchange its windows with
+    fn workspace_swap_candidates(&self, source: &str) -> Vec<String> {
+        self.workspaces
+            .iter()
+            .map(|ws| ws.name.clone())
+            .filter(|name| name != source)
+            .collect()
+    }
+
+    fn handle_workspace_swap_confirm(&mut self) -> Result<()> {
+        if let InputMode::WorkspaceSwapPicker { source, index } = self.input_mode.clone() {
+            self.input_mode = InputMode::Normal;
+            if let Some(target) = self.workspace_swap_candidates(&source).get(index) {
+                swap::swap_workspaces(&source, target)?;
+                self.refresh();
+                self.update_status(&format!("Swapped workspaces {} and {}", source, target));
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_screenshot(&mut self) -> Result<()> {
+        let dir = screenshot::screenshot_dir();
+        let options = ScreenshotOptions::default();
+        if let HomeLayoutSelectedWindow::Workspaces = self.hl().selected {
+            let windows: Vec<Window> = self
+                .ws_trees
+                .get(&self.selected_workspace())
+                .map(|tree| tree.layout_leaves())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(window, _)| window)
+                .collect();
+            let (written, failed) =
+                screenshot::screenshot_windows(&windows, &dir, "png", &options)?;
+            for failure in &failed {
+                log::record(Level::Warning, failure.clone());
+            }
+            let mut message = format!("Saved {} screenshots to {}", written.len(), dir.display());
+            let mut level = Level::Info;
+            if !failed.is_empty() {
+                message.push_str(&format!(" ({} failed, see the log)", failed.len()));
+                level = Level::Warning;
+            }
+            self.set_status(level, &message);
+            return Ok(());
+        }
+
+        let Some(window) = self.selected_target_window() else {
+            self.update_status("No window selected");
+            return Ok(());
+        };
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.png", screenshot::file_stem(&window)));
+        screenshot::screenshot_window(&window, &path, &options)?;
+        self.update_status(&format!("Saved {}", path.display()));
+        Ok(())
+    }
+
+    fn open_cleanup(&mut self) -> Result<()> {
+        let findings = cleanup::scan_current()?;
+        if findings.is_empty() {
+            self.update_status("Nothing to clean up");
+            return Ok(());
+        }
+        self.input_mode = InputMode::Cleanup {
+            // Only the windows of dead processes are sure to be garbage
+            checked: findings
+                .iter()
+                .map(|finding| matches!(finding.issue, Issue::DeadProcess(_)))
+                .collect(),
+            findings,
+            index: 0,
+            target: self.selected_workspace(),
+        };
+        Ok(())
+    }
+
+    fn handle_cleanup_fix(&mut self, msg: &Message) -> Result<()> {
+        if let InputMode::Cleanup {
+            findings,
+            checked,
+            target,
+            ..
+        } = self.input_mode.clone()
+        {
+            self.input_mode = InputMode::Normal;
+            let fix = match msg {
+                Message::CleanupMove => Fix::Relocate(target),
+                _ => Fix::Close,
+            };
+            let checked: Vec<Finding> = findings
+                .into_iter()
+                .zip(checked)
+                .filter_map(|(finding, checked)| checked.then_some(finding))
+                .collect();
+            if checked.is_empty() {
+                self.update_status("Nothing checked");
+                return Ok(());
+            }
+            let prompt = match &fix {
+                Fix::Close => format!("Close {} windows and workspaces?", checked.len()),
+                Fix::Relocate(target) => format!("Move {} windows to {}?", checked.len(), target),
+            };
+            self.input_mode = InputMode::Confirm {
+                prompt,
+                action: PendingAction::Cleanup(checked, fix),
+            };
+        }
+        Ok(())
+    }
+
+    fn run_pending_action(&mut self, action: PendingAction) -> Result<()> {
+        match action {
+            PendingAction::Cleanup(findings, fix) => {
+                let focused = self
+                    .workspaces
+                    .iter()
+                    .find(|ws| ws.focused)
+                    .map(|ws| ws.name.clone())
+                    .unwrap_or_default();
+                let handled = cleanup::apply(&findings, &fix, &focused)?;
+                self.refresh();
+                self.update_status(&format!("Cleaned up {} windows and workspaces", handled));
+            }
+            PendingAction::CloseClass(group) => {
+                group.kill_with(KillMethod::Close)?;
+                self.refresh();
+                self.update_status(&format!(
+                    "Closed {} window(s) of {}",
+                    group.windows.len(),
+                    group.class
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The windows of the selected workspace, other than the one being grabbed
+    fn grab_others(&self, grabbed: &Window) -> Vec<Window> {
+        self.workspace_windows(&self.selected_workspace())
+            .unwrap()
+            .into_iter()
+            .filter(|w| w.id != grabbed.id)
+            .collect()
+    }
+
+    /// Names of the selected workspace's windows with the grabbed one inserted where it would
+    /// be dropped
+    fn grab_preview(&self, grabbed: &Window) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .grab_others(grabbed)
+            .iter()
+            .map(|w| self.window_str(w))
+            .collect();
+        let position = (self.hl().attached_windows_index as usize).min(names.len());
+        names.insert(position, format!(">> {}", grabbed.name_str()));
+        names
+    }
+
+    fn start_grab(&mut self) {
+        let hl = self.hl();
+        if self.class_view {
+            self.update_status("Leave the class view to grab windows");
+            return;
+        }
+        if let HomeLayoutSelectedWindow::Attached = hl.selected {
+            if let Some(window) = self.selected_attached_window() {
+                self.input_mode = InputMode::Grab {
+                    window,
+                    origin_workspace: hl.workspaces_index,
+                    origin_index: hl.attached_windows_index,
+                };
+            }
+        }
+    }
+
+    /// Move the grabbed window one slot up or down, spilling over to the neighbouring workspace
+    /// when reaching either end of the list.
+    fn grab_step(&mut self, down: bool) {
+        let InputMode::Grab { window, .. } = self.input_mode.clone() else {
+            return;
+        };
+        let n_workspaces = self.workspaces.len();
+        let position = self.hl().attached_windows_index as usize;
+        let n_others = self.grab_others(&window).len();
+
+        if down {
+            if position < n_others {
+                self.hl_mut().attached_windows_index += 1;
+            } else {
+                self.hl_mut().increment_workspace_index(n_workspaces);
+                self.hl_mut().attached_windows_index = 0;
+            }
+        } else if position > 0 {
+            self.hl_mut().attached_windows_index -= 1;
+        } else {
+            self.hl_mut().decrement_workspace_index(n_workspaces);
+            let n_others = self.grab_others(&window).len();
+            self.hl_mut().attached_windows_index = n_others as u64;
+        }
+    }
+
+    /// Carry the grabbed window over to the next or previous workspace
+    fn grab_switch_workspace(&mut self, next: bool) {
+        let InputMode::Grab { window, .. } = self.input_mode.clone() else {
+            return;
+        };
+        let n_workspaces = self.workspaces.len();
+        if next {
+            self.hl_mut().increment_workspace_index(n_workspaces);
+        } else {
+            self.hl_mut().decrement_workspace_index(n_workspaces);
+        }
+        let n_others = self.grab_others(&window).len() as u64;
+        let hl = self.hl_mut();
+        hl.attached_windows_index = hl.attached_windows_index.min(n_others);
+    }
+
+    /// Drop the grabbed window at the previewed position
+    fn handle_drop(&mut self) -> Result<()> {
+        let InputMode::Grab { window, .. } = self.input_mode.clone() else {
+            return Ok(());
+        };
+        self.input_mode = InputMode::Normal;
+
+        let workspace = self.selected_workspace();
+        let others = self.grab_others(&window);
+        let position = (self.hl().attached_windows_index as usize).min(others.len());
+
+        if others.is_empty() {
+            shutils::move_window_to_workspace(window.id, &workspace)?;
+        } else if let Some(anchor) = others.get(position) {
+            shutils::move_window_next_to(window.id, anchor.id, true)?;
+        } else {
+            shutils::move_window_next_to(window.id, others[others.len() - 1].id, false)?;
+        }
+
+        self.refresh();
+        self.hl_mut().attached_windows_index = position as u64;
+        self.update_status(&format!(
+            "Dropped {} on workspace {} at position {}",
+            window.name.unwrap_or_default(),
+            workspace,
+            position
+        ));
+        Ok(())
+    }
+
     fn n_attached_windows(&self) -> usize {
         self.workspace_windows(&self.selected_workspace())
             .unwrap()
@@ -655,17 +2403,302 @@ impl Model {
     ///
     /// Updates the model in place.
     pub(crate) fn refresh(&mut self) {
-        self.ws_map = list_workspaces_and_windows();
-        self.ws_map_names = list_workspaces_and_window_names();
-        self.fcsd_window = get_focused_window();
-        self.ws_map_names = list_workspaces_and_window_names();
-        self.floating_windows = list_floating_windows();
-        self.attached_windows = list_attached_windows();
-        self.workspaces = list_workspaces();
-        self.windows = list_windows();
+        let snapshot = match fresh_snapshot() {
+            Ok(snapshot) => snapshot,
+            Err(err) => return self.report_error(err),
+        };
+        let selection = self.selection();
+        self.ws_map = snapshot.ws_map;
+        self.ws_map_names = snapshot.ws_map_names;
+        self.ws_trees = snapshot.ws_trees;
+        self.fcsd_window = snapshot.focused;
+        self.floating_windows = snapshot.floating_windows;
+        self.docks = snapshot.docks;
+        self.attached_windows = snapshot.attached_windows;
+        self.workspaces = snapshot.workspaces;
+        self.windows = snapshot.windows;
+        self.sort_workspaces();
+        self.restore_selection(selection);
+        self.process_monitor.refresh(&self.windows);
+        self.load_screen_data();
         self.update_status("Refreshed");
     }
 
+    /// Whether keys and clicks on the Attached pane go to the class view
+    fn in_class_view(&self) -> bool {
+        self.class_view
+            && self.screen == AppScreen::Home
+            && self.hl().selected == HomeLayoutSelectedWindow::Attached
+    }
+
+    fn class_view_lines(&self) -> Vec<String> {
+        let groups = &self.class_groups;
+        groups::rows(groups, &self.collapsed_classes)
+            .into_iter()
+            .map(|row| match row {
+                GroupRow::Group(group) => {
+                    let group = &groups[group];
+                    group.header_str(self.collapsed_classes.contains(&group.class))
+                }
+                GroupRow::Window(group, window) => {
+                    let grouped = &groups[group].windows[window];
+                    format!(
+                        "    {:>4}  {}",
+                        grouped.workspace,
+                        self.window_str(&grouped.window)
+                    )
+                }
+            })
+            .collect()
+    }
+
+    /// The group the selected line of the class view belongs to, along with that line
+    fn selected_class_row(&self) -> Option<(ClassGroup, GroupRow)> {
+        let groups = &self.class_groups;
+        let rows = groups::rows(groups, &self.collapsed_classes);
+        let row = *rows.get(self.class_view_index as usize)?;
+        let (GroupRow::Group(group) | GroupRow::Window(group, _)) = row;
+        Some((groups[group].clone(), row))
+    }
+
+    fn class_view_step(&mut self, down: bool) {
+        let n_rows = groups::rows(&self.class_groups, &self.collapsed_classes).len() as u64;
+        self.class_view_index = if down {
+            (self.class_view_index + 1).min(n_rows.saturating_sub(1))
+        } else {
+            self.class_view_index.saturating_sub(1)
+        };
+    }
+
+    /// Collapse or expand the selected group, or focus the selected window
+    fn class_view_goto(&mut self) -> Result<()> {
+        let Some((group, row)) = self.selected_class_row() else {
+            return Ok(());
+        };
+        match row {
+            GroupRow::Group(_) => {
+                if !self.collapsed_classes.remove(&group.class) {
+                    self.collapsed_classes.insert(group.class);
+                }
+            }
+            GroupRow::Window(_, window) => {
+                let window = &group.windows[window].window;
+                window.focus_window()?;
+                self.update_status(&format!("Focused: {}", window.name_str()));
+                self.finish_action(self.settings.after.goto)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_class_action(&mut self, msg: &Message) -> Result<()> {
+        let Some((group, _)) = self.selected_class_row() else {
+            return Ok(());
+        };
+        match msg {
+            Message::GatherClass => {
+                let workspace = self.selected_workspace();
+                group.gather(&workspace)?;
+                self.refresh();
+                self.update_status(&format!(
+                    "Gathered {} window(s) of {} on workspace {}",
+                    group.windows.len(),
+                    group.class,
+                    workspace
+                ));
+            }
+            Message::CloseClass => {
+                // Never close the terminal the TUI runs in along with the others
+                let mut group = group;
+                group
+                    .windows
+                    .retain(|grouped| Some(grouped.window.id) != self.own_window);
+                if group.windows.is_empty() {
+                    self.update_status("Nothing to close but the TUI itself");
+                    return Ok(());
+                }
+                self.input_mode = InputMode::Confirm {
+                    prompt: format!(
+                        "Close {} window(s) of {}?",
+                        group.windows.len(),
+                        group.class
+                    ),
+                    action: PendingAction::CloseClass(group),
+                };
+            }
+            _ => {
+                let focused = self.fcsd_window.as_ref().map(|window| window.id);
+                if let Some(window) = group.next_after(focused) {
+                    window.focus_window()?;
+                    self.refresh();
+                    self.update_status(&format!("Focused: {}", window.name_str()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn switch_screen(&mut self, screen: AppScreen) {
+        self.screen = screen;
+        self.screen_index = 0;
+        self.load_screen_data();
+    }
+
+    /// Query what the current screen shows beyond the snapshot
+    fn load_screen_data(&mut self) {
+        let outcome = match self.screen {
+            AppScreen::Outputs => list_outputs().map(|outputs| self.outputs = outputs),
+            AppScreen::Scratchpad => {
+                list_scratchpad_windows().map(|windows| self.scratchpad = windows)
+            }
+            _ => Ok(()),
+        };
+        if let Err(err) = outcome {
+            self.report_error(err);
+        }
+    }
+
+    /// Lines of the tree screen, workspace after workspace
+    fn tree_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for (_, tree) in ordered_trees(&self.workspaces, &self.ws_trees) {
+            tree.tree_lines(0, &mut lines);
+        }
+        lines
+    }
+
+    /// Scroll the current screen, or move its selection
+    fn screen_step(&mut self, forward: bool) {
+        let n_items = match self.screen {
+            AppScreen::Home => return,
+            AppScreen::Tree => self.tree_lines().len(),
+            AppScreen::Outputs => self.outputs.len(),
+            AppScreen::Scratchpad => self.scratchpad.len(),
+            AppScreen::Log => log::len(),
+        };
+        // The log is scrolled from its most recent entry, backwards
+        let forward = forward != (self.screen == AppScreen::Log);
+
+        self.screen_index = if forward {
+            (self.screen_index + 1).min(n_items.saturating_sub(1))
+        } else {
+            self.screen_index.saturating_sub(1)
+        };
+    }
+
+    fn show_scratchpad_window(&mut self) -> Result<()> {
+        if let Some(window) = self.scratchpad.get(self.screen_index).cloned() {
+            i3_cmd(&[
+                &format!(r#"[con_id="{}"]"#, window.id),
+                "scratchpad",
+                "show",
+            ])?;
+            self.refresh();
+            self.update_status(&format!("Showing: {}", window.name_str()));
+        }
+        Ok(())
+    }
+
+    /// Names offered by the completion of the command line
+    fn completion_source(&self) -> CompletionSource {
+        let properties = || {
+            self.windows
+                .iter()
+                .filter_map(|w| w.window_properties.as_ref())
+        };
+        CompletionSource {
+            workspaces: self.workspaces.iter().map(|ws| ws.name.clone()).collect(),
+            marks: palette::marks(),
+            classes: self.windows.iter().filter_map(|w| w.class_name()).collect(),
+            instances: properties().filter_map(|p| p.instance.clone()).collect(),
+        }
+    }
+
+    /// Complete the last word of the command line. The first tab inserts the first candidate,
+    /// the next ones cycle through the others.
+    fn complete_command(&mut self) {
+        let source = self.completion_source();
+        let InputMode::Command {
+            input, completion, ..
+        } = &mut self.input_mode
+        else {
+            return;
+        };
+
+        if let Some(completion) = completion {
+            completion.index = (completion.index + 1) % completion.candidates.len();
+            input.truncate(completion.start);
+            input.push_str(&completion.candidates[completion.index]);
+            return;
+        }
+
+        let (start, candidates) = palette::complete(input, &source);
+        let Some(first) = candidates.first() else {
+            return;
+        };
+        input.truncate(start);
+        input.push_str(first);
+        if candidates.len() > 1 {
+            *completion = Some(Completion {
+                start,
+                candidates,
+                index: 0,
+            });
+        } else if !first.ends_with('"') {
+            // The word is complete, get ready for the next one
+            input.push(' ');
+        }
+    }
+
+    /// Replace the command line with an older (or newer) command of the history
+    fn recall_command(&mut self, older: bool) {
+        let commands = self.command_history.commands();
+        let InputMode::Command {
+            input,
+            completion,
+            history_index,
+        } = &mut self.input_mode
+        else {
+            return;
+        };
+        if commands.is_empty() {
+            return;
+        }
+
+        *history_index = match (*history_index, older) {
+            (None, true) => Some(commands.len() - 1),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) => Some(index + 1).filter(|index| *index < commands.len()),
+        };
+        *input = history_index.map_or(String::new(), |index| commands[index].clone());
+        *completion = None;
+    }
+
+    /// Send the command line to i3 and show whether it succeeded
+    fn run_command(&mut self) -> Result<()> {
+        let InputMode::Command { input, .. } =
+            std::mem::replace(&mut self.input_mode, InputMode::Normal)
+        else {
+            return Ok(());
+        };
+        let command = input.trim();
+        if command.is_empty() {
+            return Ok(());
+        }
+
+        self.command_history.record(command);
+        let _ = self.command_history.save();
+
+        let reply = i3_cmd(&[command])?;
+        self.refresh();
+        match palette::summarize_reply(&reply) {
+            Ok(summary) => self.update_status(&format!("i3: {}", summary)),
+            Err(error) => self.set_status(Level::Error, &format!("i3 error: {}", error)),
+        }
+        Ok(())
+    }
+
     /// Retrieve a copy of the home layout (hl) of a given model
     pub(crate) fn hl(&self) -> HomeLayout {
         self.home_layout.as_ref().unwrap().clone()
@@ -701,19 +2734,67 @@ impl Model {
                     self.home_layout.as_mut().unwrap().move_right();
                 }
             },
+            Message::MoveUp | Message::MoveDown if self.screen != AppScreen::Home => {
+                self.screen_step(matches!(msg, Message::MoveDown))
+            }
+            Message::MoveUp | Message::MoveDown if self.in_class_view() => {
+                self.class_view_step(matches!(msg, Message::MoveDown))
+            }
+            Message::GoTo if self.in_class_view() => {
+                if let Err(err) = self.class_view_goto() {
+                    self.report_error(err);
+                }
+            }
+            Message::ToggleClassView => {
+                self.class_view = !self.class_view;
+                self.class_view_index = 0;
+            }
+            Message::GatherClass | Message::CloseClass | Message::CycleClass => {
+                if !self.in_class_view() {
+                    self.update_status("Press g to group the windows by class first");
+                } else if let Err(err) = self.handle_class_action(&msg) {
+                    self.report_error(err);
+                }
+            }
+            Message::GoTo if self.screen == AppScreen::Scratchpad => {
+                if let Err(err) = self.show_scratchpad_window() {
+                    self.report_error(err);
+                }
+            }
+            Message::SwitchScreen(screen) => self.switch_screen(screen),
+            Message::NextScreen | Message::PrevScreen => {
+                let n_screens = AppScreen::ALL.len();
+                let offset = if let Message::NextScreen = msg {
+                    1
+                } else {
+                    n_screens - 1
+                };
+                self.switch_screen(AppScreen::ALL[(self.screen.position() + offset) % n_screens]);
+            }
+            Message::ToggleZoom => {
+                self.pane_sizes.zoomed = !self.pane_sizes.zoomed;
+                let _ = self.pane_sizes.save();
+            }
+            Message::ResizePane(dimension, grow) => {
+                let selected = self.hl().selected;
+                self.pane_sizes.resize(selected, dimension, grow);
+                let _ = self.pane_sizes.save();
+            }
             Message::MoveUp => match self.current_menu {
                 AppLayout::HomeLayout => {
                     let n_attached = self.n_attached_windows();
+                    let n_history = self.history.entries().len();
                     if let Some(layout) = &mut self.home_layout {
-                        layout.move_up_inside(self.workspaces.len(), n_attached);
+                        layout.move_up_inside(self.workspaces.len(), n_attached, n_history);
                     }
                 }
             },
             Message::MoveDown => match self.current_menu {
                 AppLayout::HomeLayout => {
                     let n_attached = self.n_attached_windows();
+                    let n_history = self.history.entries().len();
                     if let Some(layout) = &mut self.home_layout {
-                        layout.move_down_inside(self.workspaces.len(), n_attached);
+                        layout.move_down_inside(self.workspaces.len(), n_attached, n_history);
                     }
                 }
             },
@@ -730,29 +2811,325 @@ impl Model {
             Message::Add => match self.current_menu {
                 AppLayout::HomeLayout => {
                     let mut hl = self.home_layout.clone().unwrap();
-                    hl.handle_add(self).unwrap();
+                    if let Err(err) = hl.handle_add(self) {
+                        self.report_error(err);
+                    }
                 }
             },
             Message::Consolidate => match self.current_menu {
                 AppLayout::HomeLayout => {
                     let mut hl = self.hl();
-                    hl.handle_consolidate(&self.selected_workspace(), self)
-                        .unwrap();
+                    if let Err(err) = hl.handle_consolidate(&self.selected_workspace(), self) {
+                        self.report_error(err);
+                    }
                     self.refresh()
                 }
             },
             Message::GoTo => match self.current_menu {
                 AppLayout::HomeLayout => {
-                    self.handle_goto().unwrap();
+                    if let Err(err) = self.handle_goto() {
+                        self.report_error(err);
+                    }
                 }
             },
             Message::Delete => match self.current_menu {
                 AppLayout::HomeLayout => {
-                    self.handle_delete().unwrap();
+                    if let Err(err) = self.handle_delete() {
+                        self.report_error(err);
+                    }
+                }
+            },
+            Message::EnterLayoutMode => match self.selected_target_window() {
+                Some(_) => self.input_mode = InputMode::Layout,
+                None => self.update_status("Select an attached or the focused window first"),
+            },
+            Message::Container(command) => {
+                if let Err(err) = self.apply_container_command(command) {
+                    self.report_error(err);
+                }
+            }
+            Message::Resize { grow, dimension } => {
+                let command = ContainerCommand::Resize {
+                    grow,
+                    dimension,
+                    ppt: self.settings.resize_step,
+                };
+                if let Err(err) = self.apply_container_command(command) {
+                    self.report_error(err);
+                }
+            }
+            Message::OpenSwapPicker => {
+                if let Some(source) = self.selected_target_window() {
+                    self.input_mode = InputMode::SwapPicker { source, index: 0 };
+                }
+            }
+            Message::OpenWorkspaceSwapPicker => {
+                if let HomeLayoutSelectedWindow::Workspaces = self.hl().selected {
+                    if !self.workspaces.is_empty() {
+                        self.input_mode = InputMode::WorkspaceSwapPicker {
+                            source: self.selected_workspace(),
+                            index: 0,
+                        };
+                    }
+                }
+            }
+            Message::Screenshot => {
+                if let Err(err) = self.handle_screenshot() {
+                    self.report_error(err);
+                }
+            }
+            Message::ConfirmYes => {
+                if let InputMode::Confirm { action, .. } = self.input_mode.clone() {
+                    self.input_mode = InputMode::Normal;
+                    if let Err(err) = self.run_pending_action(action) {
+                        self.report_error(err);
+                    }
+                }
+            }
+            Message::OpenCleanup => {
+                if let Err(err) = self.open_cleanup() {
+                    self.report_error(err);
+                }
+            }
+            Message::CleanupToggle => {
+                if let InputMode::Cleanup { checked, index, .. } = &mut self.input_mode {
+                    if let Some(checked) = checked.get_mut(*index) {
+                        *checked = !*checked;
+                    }
+                }
+            }
+            Message::CleanupClose | Message::CleanupMove => {
+                if let Err(err) = self.handle_cleanup_fix(&msg) {
+                    self.report_error(err);
+                }
+            }
+            Message::OpenTemplatePicker => {
+                if let HomeLayoutSelectedWindow::Workspaces = self.hl().selected {
+                    match load_templates() {
+                        Ok(templates) => {
+                            self.input_mode = InputMode::TemplatePicker {
+                                names: templates.into_keys().collect(),
+                                index: 0,
+                            }
+                        }
+                        Err(err) => self.report_error(err),
+                    }
+                }
+            }
+            Message::PickerUp | Message::PickerDown => {
+                if let InputMode::Help { scroll } = &mut self.input_mode {
+                    *scroll = if let Message::PickerUp = msg {
+                        scroll.saturating_sub(1)
+                    } else {
+                        *scroll + 1
+                    };
+                    return None;
+                }
+                let n_items = match &self.input_mode {
+                    InputMode::ContextMenu { items, .. } => items.len(),
+                    _ => self.picker().map_or(0, |(_, items, _)| items.len()),
+                }
+                .max(1);
+                if let InputMode::SwapPicker { index, .. }
+                | InputMode::WorkspaceSwapPicker { index, .. }
+                | InputMode::Cleanup { index, .. }
+                | InputMode::TemplatePicker { index, .. }
+                | InputMode::ContextMenu { index, .. } = &mut self.input_mode
+                {
+                    *index = if let Message::PickerUp = msg {
+                        (*index + n_items - 1) % n_items
+                    } else {
+                        (*index + 1) % n_items
+                    };
+                }
+            }
+            Message::PickerConfirm => {
+                if let InputMode::ContextMenu { items, index, .. } = &self.input_mode {
+                    let message = items.get(*index).map(|(_, message)| message.clone());
+                    self.input_mode = InputMode::Normal;
+                    return message;
+                }
+
+                let outcome = match self.input_mode {
+                    InputMode::TemplatePicker { .. } => self.handle_template_confirm(),
+                    InputMode::WorkspaceSwapPicker { .. } => self.handle_workspace_swap_confirm(),
+                    _ => self.handle_swap_confirm(),
+                };
+                if let Err(err) = outcome {
+                    self.report_error(err);
+                }
+            }
+            Message::HistoryPrevious | Message::HistoryNext => {
+                let older = matches!(msg, Message::HistoryPrevious);
+                let alive: Vec<u64> = self.windows.iter().map(|w| w.id).collect();
+                if let Some(entry) = self.history.step(older, &alive) {
+                    match entry.focus() {
+                        Ok(_) => self.update_status(&format!("Focused: {}", entry.name_str())),
+                        Err(err) => self.report_error(err),
+                    }
+                    let _ = self.history.save();
+                }
+            }
+            Message::OpenCommandLine => {
+                self.input_mode = InputMode::Command {
+                    input: String::new(),
+                    completion: None,
+                    history_index: None,
+                }
+            }
+            Message::CommandInput(_) | Message::CommandBackspace => {
+                let input = match &mut self.input_mode {
+                    InputMode::Command {
+                        input, completion, ..
+                    } => {
+                        *completion = None;
+                        input
+                    }
+                    InputMode::Text { input, .. } => input,
+                    _ => return None,
+                };
+                match msg {
+                    Message::CommandInput(c) => input.push(c),
+                    _ => {
+                        input.pop();
+                    }
+                }
+            }
+            Message::EditWorkspaceLabel | Message::EditWorkspaceNote => {
+                let workspace = self.selected_workspace();
+                let note = self
+                    .workspace_notes
+                    .get(&workspace)
+                    .cloned()
+                    .unwrap_or_default();
+                self.input_mode = match msg {
+                    Message::EditWorkspaceLabel => InputMode::Text {
+                        field: TextField::Label(workspace),
+                        input: note.label,
+                    },
+                    _ => InputMode::Text {
+                        field: TextField::Note(workspace),
+                        input: note.note,
+                    },
+                };
+            }
+            Message::TextConfirm => self.save_workspace_note(),
+            Message::CancelToOrigin => {
+                let result = self
+                    .origin
+                    .restore()
+                    .and_then(|_| self.finish_action(self.settings.after.cancel));
+                if let Err(err) = result {
+                    self.report_error(err);
+                }
+            }
+            Message::PushWorkspaceLabel => {
+                if let Err(err) = self.push_workspace_label() {
+                    self.report_error(err);
+                }
+            }
+            Message::CommandComplete => self.complete_command(),
+            Message::CommandOlder => self.recall_command(true),
+            Message::CommandNewer => self.recall_command(false),
+            Message::CommandRun => {
+                if let Err(err) = self.run_command() {
+                    self.report_error(err);
+                }
+            }
+            Message::CycleWorkspaceSort => self.cycle_workspace_sort(),
+            Message::ExportTree => {
+                match export::export_all(&ordered_trees(&self.workspaces, &self.ws_trees)) {
+                    Ok(dir) => {
+                        self.update_status(&format!("Exported the tree to {}", dir.display()))
+                    }
+                    Err(err) => self.report_error(err),
+                }
+            }
+            Message::ShowLog => {
+                if self.screen != AppScreen::Log {
+                    self.screen_before_log = self.screen;
+                }
+                self.switch_screen(self.screen.toggle_log(self.screen_before_log));
+            }
+            Message::ShowHelp => self.input_mode = InputMode::Help { scroll: 0 },
+            Message::ShowRules => {
+                self.sync_rules(false);
+                self.input_mode = InputMode::Rules;
+            }
+            Message::ReloadRules => self.sync_rules(true),
+            Message::EditRules => self.pending_edit = Some(config::config_file(RULES_FILE)),
+            Message::StartGrab => self.start_grab(),
+            Message::GrabDown => self.grab_step(true),
+            Message::GrabUp => self.grab_step(false),
+            Message::GrabNextWorkspace => self.grab_switch_workspace(true),
+            Message::GrabPrevWorkspace => self.grab_switch_workspace(false),
+            Message::Drop => {
+                if let Err(err) = self.handle_drop() {
+                    self.report_error(err);
+                }
+            }
+            Message::Cancel => {
+                if let InputMode::Grab {
+                    origin_workspace,
+                    origin_index,
+                    ..
+                } = self.input_mode
+                {
+                    let hl = self.hl_mut();
+                    hl.workspaces_index = origin_workspace;
+                    hl.attached_windows_index = origin_index;
+                }
+                self.input_mode = InputMode::Normal;
+            }
+            Message::Kill(method) => match self.current_menu {
+                AppLayout::HomeLayout => {
+                    if let Err(err) = self.handle_kill(method) {
+                        self.report_error(err);
+                    }
                 }
             },
-            _ => (),
         }
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::fixture;
+
+    #[test]
+    fn follow_selected_items() {
+        // Still there, at another position
+        assert_eq!(follow(&[4, 7, 9], Some(7), 0), 1);
+        // Gone: its successor takes its place, or its predecessor when it was the last one
+        assert_eq!(follow(&[4, 9], Some(7), 1), 1);
+        assert_eq!(follow(&[4, 7], Some(9), 2), 1);
+        assert_eq!(follow(&[], Some(9), 2), 0);
+        assert_eq!(follow(&[4, 7], None, 0), 0);
+    }
+
+    #[test]
+    fn toggle_the_log() {
+        let log = AppScreen::Tree.toggle_log(AppScreen::Home);
+        assert_eq!(log, AppScreen::Log);
+        assert_eq!(log.toggle_log(AppScreen::Tree), AppScreen::Tree);
+    }
+
+    #[test]
+    fn origin_skips_the_tui() {
+        let window = |id: u64| fixture::window(id, None, "term");
+        let workspaces: Vec<Workspace> = serde_json::from_str(
+            r#"[{"id": 1, "name": "1"}, {"id": 2, "name": "2: web", "focused": true}]"#,
+        )
+        .unwrap();
+        let mut history = History::default();
+        history.record(&window(7));
+        history.record(&window(9));
+
+        let origin = Origin::capture(&workspaces, &history, Some(9));
+        assert_eq!(origin.workspace, "2: web");
+        assert_eq!(origin.window, Some(7));
+        assert_eq!(Origin::capture(&workspaces, &history, None).window, Some(9));
+    }
+}
@@ -0,0 +1,109 @@
+//! Labels and notes attached to workspaces, kept by i3-mgr since i3 has no place for them.
+//!
+//! A label can also be pushed into the name of the workspace, following i3's `num:label`
+//! convention, so that it shows up in i3bar.
+
+use crate::prelude::*;
+use crate::state;
+use crate::workspace::Workspace;
+use std::collections::BTreeMap;
+
+const NOTES_FILE: &str = "workspace_notes.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub(crate) struct WorkspaceNote {
+    /// A few words, e.g. `ticket-123`
+    pub(crate) label: String,
+    /// Free-form text, e.g. `debugging the flaky login test`
+    pub(crate) note: String,
+}
+
+impl WorkspaceNote {
+    fn is_empty(&self) -> bool {
+        self.label.is_empty() && self.note.is_empty()
+    }
+}
+
+/// Notes of every workspace, keyed by workspace name
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct WorkspaceNotes {
+    notes: BTreeMap<String, WorkspaceNote>,
+}
+
+impl WorkspaceNotes {
+    pub(crate) fn load() -> Self {
+        state::load(NOTES_FILE)
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        state::save(NOTES_FILE, self)
+    }
+
+    pub(crate) fn get(&self, workspace: &str) -> Option<&WorkspaceNote> {
+        self.notes.get(workspace)
+    }
+
+    /// Change the note of a workspace, forgetting it once both fields are empty
+    pub(crate) fn update(&mut self, workspace: &str, change: impl FnOnce(&mut WorkspaceNote)) {
+        let note = self.notes.entry(workspace.to_string()).or_default();
+        change(note);
+        if note.is_empty() {
+            self.notes.remove(workspace);
+        }
+    }
+
+    /// Follow a workspace that got renamed
+    pub(crate) fn rename(&mut self, from: &str, to: &str) {
+        if let Some(note) = self.notes.remove(from) {
+            self.notes.insert(to.to_string(), note);
+        }
+    }
+
+    /// Suffix of the workspace in the Workspaces pane, e.g. ` [ticket-123] debugging`
+    pub(crate) fn describe(&self, workspace: &Workspace) -> String {
+        let Some(note) = self.notes.get(&workspace.name) else {
+            return String::new();
+        };
+        let mut description = String::new();
+        if !note.label.is_empty() && workspace.name != labelled_name(workspace, &note.label) {
+            description.push_str(&format!(" [{}]", note.label));
+        }
+        if !note.note.is_empty() {
+            description.push_str(&format!(" {}", note.note));
+        }
+        description
+    }
+}
+
+/// The name giving `workspace` the label, keeping its number: `3:ticket-123`
+pub(crate) fn labelled_name(workspace: &Workspace, label: &str) -> String {
+    match (workspace.num >= 0, label.is_empty()) {
+        (true, true) => workspace.num.to_string(),
+        (true, false) => format!("{}:{}", workspace.num, label),
+        (false, _) => label.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_workspaces() {
+        let mut workspace: Workspace =
+            serde_json::from_str(r#"{"id": 1, "name": "3", "num": 3}"#).unwrap();
+        let mut notes = WorkspaceNotes::default();
+        notes.update("3", |note| note.label = "ticket-123".to_string());
+        notes.update("3", |note| note.note = "debugging".to_string());
+        assert_eq!(notes.describe(&workspace), " [ticket-123] debugging");
+
+        assert_eq!(labelled_name(&workspace, "ticket-123"), "3:ticket-123");
+        notes.rename("3", "3:ticket-123");
+        workspace.name = "3:ticket-123".to_string();
+        assert_eq!(notes.describe(&workspace), " debugging");
+
+        notes.update("3:ticket-123", |note| *note = WorkspaceNote::default());
+        assert!(notes.get("3:ticket-123").is_none());
+    }
+}
@@ -0,0 +1,47 @@
+//! Facilities to work with the outputs (monitors) known to i3.
+
+use crate::prelude::*;
+use crate::shutils::{cmd, pipe};
+use crate::window::Geometry;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Output {
+    pub(crate) name: String,
+    pub(crate) active: bool,
+    pub(crate) primary: bool,
+    pub(crate) rect: Geometry,
+    /// The workspace visible on this output, none when it is disabled
+    pub(crate) current_workspace: Option<String>,
+}
+
+impl Output {
+    pub(crate) fn output_str(&self) -> String {
+        format!(
+            "{:12} {:8} {}x{}+{}+{} {}{}",
+            self.name,
+            if self.active { "active" } else { "disabled" },
+            self.rect.width,
+            self.rect.height,
+            self.rect.x,
+            self.rect.y,
+            self.current_workspace
+                .as_ref()
+                .map_or(String::new(), |ws| format!("showing {}", ws)),
+            if self.primary { " (primary)" } else { "" },
+        )
+    }
+}
+
+/// List all the outputs, including the disabled ones
+pub(crate) fn list_outputs() -> Result<Vec<Output>> {
+    let mut cmds = [
+        &mut cmd(&["i3-msg", "-t", "get_outputs"]),
+        &mut cmd(&[
+            "jq",
+            "-r",
+            "[.[] | {name, active, primary, rect, current_workspace}]",
+        ]),
+    ];
+
+    Ok(serde_json::from_str(&pipe(&mut cmds)?)?)
+}
@@ -0,0 +1,344 @@
+//! Command palette: raw i3 commands typed in the TUI, with tab-completion and a history that
+//! is kept between sessions.
+
+use crate::prelude::*;
+use crate::shutils::{cmd, pipe, quoted};
+use crate::state;
+use serde_json::Value;
+
+const COMMANDS_FILE: &str = "commands.json";
+const MAX_COMMANDS: usize = 100;
+
+/// Verbs of the i3 command language
+const VERBS: &[&str] = &[
+    "append_layout",
+    "bar",
+    "border",
+    "exec",
+    "exit",
+    "floating",
+    "focus",
+    "fullscreen",
+    "gaps",
+    "kill",
+    "layout",
+    "mark",
+    "mode",
+    "move",
+    "nop",
+    "open",
+    "reload",
+    "rename",
+    "resize",
+    "restart",
+    "scratchpad",
+    "split",
+    "sticky",
+    "swap",
+    "title_format",
+    "unmark",
+    "workspace",
+];
+
+/// Attributes that can be used between brackets to select containers
+const CRITERIA: &[&str] = &[
+    "class",
+    "instance",
+    "window_role",
+    "window_type",
+    "title",
+    "urgent",
+    "workspace",
+    "con_id",
+    "con_mark",
+    "id",
+    "floating",
+    "tiling",
+];
+
+/// Workspace names that have a special meaning for i3
+const WORKSPACE_KEYWORDS: &[&str] = &[
+    "next",
+    "prev",
+    "next_on_output",
+    "prev_on_output",
+    "back_and_forth",
+    "number",
+];
+
+/// Words that can follow a verb
+fn arguments(verb: &str) -> &'static [&'static str] {
+    match verb {
+        "border" => &["none", "normal", "pixel", "toggle"],
+        "exec" => &["--no-startup-id"],
+        "floating" | "fullscreen" | "sticky" => &["enable", "disable", "toggle"],
+        "focus" => &[
+            "left",
+            "right",
+            "up",
+            "down",
+            "parent",
+            "child",
+            "floating",
+            "tiling",
+            "mode_toggle",
+            "next",
+            "prev",
+            "output",
+        ],
+        "kill" => &["window", "client"],
+        "layout" => &[
+            "default", "tabbed", "stacking", "splitv", "splith", "toggle",
+        ],
+        "mark" => &["--add", "--replace", "--toggle"],
+        "move" => &[
+            "left",
+            "right",
+            "up",
+            "down",
+            "container",
+            "window",
+            "workspace",
+            "output",
+            "scratchpad",
+            "position",
+            "absolute",
+        ],
+        "rename" => &["workspace"],
+        "resize" => &["grow", "shrink", "set"],
+        "scratchpad" => &["show"],
+        "split" => &["vertical", "horizontal", "toggle", "v", "h", "t"],
+        "swap" => &["container"],
+        _ => &[],
+    }
+}
+
+/// Commands run from the palette, most recent last
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct CommandHistory {
+    commands: Vec<String>,
+}
+
+impl CommandHistory {
+    /// Load the commands run during previous sessions
+    pub(crate) fn load() -> Self {
+        state::load(COMMANDS_FILE)
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        state::save(COMMANDS_FILE, self)
+    }
+
+    pub(crate) fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    pub(crate) fn record(&mut self, command: &str) {
+        self.commands.retain(|c| c != command);
+        self.commands.push(command.to_string());
+        if self.commands.len() > MAX_COMMANDS {
+            self.commands.remove(0);
+        }
+    }
+}
+
+/// Candidates being cycled through with repeated tab presses
+#[derive(Debug, Clone)]
+pub(crate) struct Completion {
+    /// Where the completed word starts in the input
+    pub(crate) start: usize,
+    pub(crate) candidates: Vec<String>,
+    pub(crate) index: usize,
+}
+
+/// Names that the completion draws from, beyond the fixed vocabulary of i3
+#[derive(Debug, Default)]
+pub(crate) struct CompletionSource {
+    pub(crate) workspaces: Vec<String>,
+    pub(crate) marks: Vec<String>,
+    pub(crate) classes: Vec<String>,
+    pub(crate) instances: Vec<String>,
+}
+
+/// All the marks currently set
+pub(crate) fn marks() -> Vec<String> {
+    pipe(&mut [&mut cmd(&["i3-msg", "-t", "get_marks"])])
+        .ok()
+        .and_then(|reply| serde_json::from_str(&reply).ok())
+        .unwrap_or_default()
+}
+
+/// Candidates starting with `prefix`, ignoring the quotes around names
+fn starting_with(candidates: &[String], prefix: &str) -> Vec<String> {
+    let prefix = prefix.trim_start_matches('"');
+    let mut matches: Vec<String> = candidates
+        .iter()
+        .filter(|c| c.trim_start_matches('"').starts_with(prefix))
+        .cloned()
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+/// Candidates for the word being typed at the end of `input`.
+///
+/// Returns the byte offset where that word starts, so that it can be replaced by any of the
+/// candidates.
+pub(crate) fn complete(input: &str, source: &CompletionSource) -> (usize, Vec<String>) {
+    // Within criteria, e.g. `[class="Fire`
+    if let Some(open) = input
+        .rfind('[')
+        .filter(|open| !input[*open..].contains(']'))
+    {
+        let start = input[open..]
+            .rfind(' ')
+            .map_or(open + 1, |space| open + space + 1);
+        let criterion = &input[start..];
+
+        let candidates = match criterion.split_once("=\"") {
+            Some((key, value)) => {
+                let values = match key {
+                    "class" => &source.classes,
+                    "instance" => &source.instances,
+                    "workspace" => &source.workspaces,
+                    "con_mark" => &source.marks,
+                    _ => return (start, vec![]),
+                };
+                starting_with(values, value)
+                    .into_iter()
+                    .map(|value| format!("{}=\"{}\"", key, value))
+                    .collect()
+            }
+            None => CRITERIA
+                .iter()
+                .filter(|key| key.starts_with(criterion))
+                .map(|key| format!("{}=\"", key))
+                .collect(),
+        };
+        return (start, candidates);
+    }
+
+    let start = input
+        .rfind(|c: char| c.is_whitespace() || c == ';' || c == ',' || c == ']')
+        .map_or(0, |sep| sep + 1);
+    let word = &input[start..];
+
+    // Words of the current command, leaving the criteria out
+    let command_start = input[..start]
+        .rfind([';', ',', ']'])
+        .map_or(0, |sep| sep + 1);
+    let previous: Vec<&str> = input[command_start..start].split_whitespace().collect();
+
+    let vocabulary: Vec<String> = match previous.as_slice() {
+        [] => VERBS.iter().map(|v| v.to_string()).collect(),
+        [.., "workspace"] => WORKSPACE_KEYWORDS
+            .iter()
+            .map(|k| k.to_string())
+            .chain(source.workspaces.iter().map(|w| quoted(w)))
+            .collect(),
+        ["mark"] => arguments("mark")
+            .iter()
+            .map(|a| a.to_string())
+            .chain(source.marks.iter().map(|m| quoted(m)))
+            .collect(),
+        [.., "mark"] | ["mark", _] | ["unmark"] => source.marks.iter().map(|m| quoted(m)).collect(),
+        [verb] => arguments(verb).iter().map(|a| a.to_string()).collect(),
+        _ => vec![],
+    };
+    (start, starting_with(&vocabulary, word))
+}
+
+/// Turn the JSON reply of i3 into a line for the status bar, failing if any command failed.
+pub(crate) fn summarize_reply(reply: &str) -> std::result::Result<String, String> {
+    let outcomes: Vec<Value> =
+        serde_json::from_str(reply).map_err(|_| format!("Unexpected reply: {}", reply.trim()))?;
+
+    let errors: Vec<String> = outcomes
+        .iter()
+        .filter(|outcome| outcome["success"] != Value::Bool(true))
+        .map(|outcome| {
+            outcome["error"]
+                .as_str()
+                .unwrap_or("unknown error")
+                .to_string()
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(format!("success ({} command(s))", outcomes.len()))
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source() -> CompletionSource {
+        CompletionSource {
+            workspaces: vec!["1".to_string(), "2: web".to_string()],
+            marks: vec!["logs".to_string()],
+            classes: vec!["Firefox".to_string(), "Alacritty".to_string()],
+            instances: vec![],
+        }
+    }
+
+    #[test]
+    fn complete_verbs_and_arguments() {
+        assert_eq!(complete("fl", &source()), (0, vec!["floating".to_string()]));
+        assert_eq!(
+            complete("split t", &source()),
+            (6, vec!["t".to_string(), "toggle".to_string()])
+        );
+        assert_eq!(
+            complete("focus left; kill c", &source()),
+            (17, vec!["client".to_string()])
+        );
+        assert_eq!(complete("move to mark l", &source()).1, vec!["\"logs\""]);
+    }
+
+    #[test]
+    fn complete_workspaces() {
+        let (start, candidates) = complete("move container to workspace 2", &source());
+        assert_eq!(start, 28);
+        assert_eq!(candidates, vec!["\"2: web\"".to_string()]);
+    }
+
+    #[test]
+    fn complete_workspaces_with_special_characters() {
+        let source = CompletionSource {
+            workspaces: vec!["a,b".to_string(), r#"say "hi""#.to_string()],
+            ..Default::default()
+        };
+        assert_eq!(complete("workspace a", &source).1, vec![r#""a,b""#]);
+        assert_eq!(complete("workspace s", &source).1, vec![r#""say \"hi\"""#]);
+    }
+
+    #[test]
+    fn complete_criteria() {
+        assert_eq!(
+            complete("[con_", &source()),
+            (1, vec!["con_id=\"".to_string(), "con_mark=\"".to_string()])
+        );
+        assert_eq!(
+            complete("[urgent=\"latest\" class=\"Fi", &source()),
+            (17, vec!["class=\"Firefox\"".to_string()])
+        );
+        assert_eq!(
+            complete("[class=\"Firefox\"] fulls", &source()),
+            (18, vec!["fullscreen".to_string()])
+        );
+    }
+
+    #[test]
+    fn summarize_replies() {
+        assert!(summarize_reply(r#"[{"success":true},{"success":true}]"#).is_ok());
+        assert_eq!(
+            summarize_reply(r#"[{"success":false,"parse_error":true,"error":"Expected one of"}]"#),
+            Err("Expected one of".to_string())
+        );
+        assert!(summarize_reply("").is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Sizes of the panes of the home screen, adjusted interactively and kept between sessions.
+
+use crate::model::HomeLayoutSelectedWindow as Pane;
+use crate::prelude::*;
+use crate::state;
+use crate::window::Dimension;
+
+const PANES_FILE: &str = "panes.json";
+/// Percentage points added or removed by a single resize
+const STEP: u16 = 5;
+/// No pane can get smaller than this percentage
+const MIN_PERCENT: u16 = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct PaneSizes {
+    /// Width of the left column, in percent
+    pub(crate) left_width: u16,
+    /// Heights of the Workspaces, Focused and Recent panes, in percent
+    pub(crate) left: [u16; 3],
+    /// Heights of the Attached and Floating panes, in percent
+    pub(crate) right: [u16; 2],
+    /// Only show the selected pane
+    pub(crate) zoomed: bool,
+}
+
+impl Default for PaneSizes {
+    fn default() -> Self {
+        PaneSizes {
+            left_width: 50,
+            left: [40, 20, 40],
+            right: [50, 50],
+            zoomed: false,
+        }
+    }
+}
+
+impl PaneSizes {
+    /// Load the sizes of the previous session
+    pub(crate) fn load() -> Self {
+        state::load(PANES_FILE)
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        state::save(PANES_FILE, self)
+    }
+
+    /// Grow (or shrink) `pane` along a dimension, at the expense of (or to the benefit of) its
+    /// neighbours.
+    pub(crate) fn resize(&mut self, pane: Pane, dimension: Dimension, grow: bool) {
+        let (column, index) = match pane {
+            Pane::Workspaces => (&mut self.left[..], 0),
+            Pane::Focused => (&mut self.left[..], 1),
+            Pane::History => (&mut self.left[..], 2),
+            Pane::Attached => (&mut self.right[..], 0),
+            Pane::Floating => (&mut self.right[..], 1),
+        };
+
+        match dimension {
+            Dimension::Height => resize_within(column, index, grow),
+            Dimension::Width => {
+                let in_left_column =
+                    matches!(pane, Pane::Workspaces | Pane::Focused | Pane::History);
+                self.left_width = if grow == in_left_column {
+                    self.left_width + STEP
+                } else {
+                    self.left_width.saturating_sub(STEP)
+                }
+                .clamp(MIN_PERCENT, 100 - MIN_PERCENT);
+            }
+        }
+    }
+}
+
+/// Grow a share by taking from the largest other one, or shrink it by giving to the next one.
+fn resize_within(shares: &mut [u16], index: usize, grow: bool) {
+    let (from, to) = if grow {
+        let largest = (0..shares.len())
+            .filter(|i| *i != index)
+            .max_by_key(|i| shares[*i]);
+        match largest {
+            Some(largest) => (largest, index),
+            None => return,
+        }
+    } else if index + 1 < shares.len() {
+        (index, index + 1)
+    } else if index > 0 {
+        (index, index - 1)
+    } else {
+        return;
+    };
+
+    if shares[from] >= MIN_PERCENT + STEP {
+        shares[from] -= STEP;
+        shares[to] += STEP;
+    }
+}
@@ -0,0 +1,109 @@
+//! The TUI as a scratchpad popup: `i3-mgr popup` shows or hides the terminal running i3-mgr,
+//! spawning it the first time.
+//!
+//! The terminal gets found by its mark, or adopted by its class when `popup_class` is set, and
+//! the TUI it runs knows it lives in the popup by finding that mark on its own window.
+
+use crate::config::Settings;
+use crate::palette;
+use crate::prelude::*;
+use crate::shutils::i3_cmd;
+use crate::window::{get_tree, list_windows};
+
+/// Mark of the terminal running the popup TUI
+pub(crate) const POPUP_MARK: &str = "_i3mgr_popup";
+
+fn criteria() -> String {
+    format!(r#"[con_mark="^{}$"]"#, POPUP_MARK)
+}
+
+/// Commands showing the freshly marked popup floating and centered, at the configured size
+fn show_commands(settings: &Settings) -> String {
+    format!(
+        r#"move scratchpad; [con_mark="^{}$"] scratchpad show, resize set {} ppt {} ppt, move position center"#,
+        POPUP_MARK, settings.popup_width, settings.popup_height
+    )
+}
+
+/// Shell command opening the TUI in a terminal that marks itself and becomes the popup. The
+/// terminal is told apart from whatever has the focus by the `WINDOWID` it sets.
+fn spawn_command(settings: &Settings) -> String {
+    let commands = format!(
+        r#"[id="$WINDOWID"] mark --add {}, {}"#,
+        POPUP_MARK,
+        show_commands(settings)
+    );
+    format!(
+        "{} -e sh -c 'i3-msg \"{}\" >/dev/null; exec i3-mgr'",
+        settings.terminal,
+        commands.replace('"', "\\\"")
+    )
+}
+
+/// Whether the window `own_window`, the one this TUI runs in, is the popup
+pub(crate) fn is_popup(own_window: Option<u64>) -> bool {
+    let Some(id) = own_window else {
+        return false;
+    };
+    get_tree().is_ok_and(|root| {
+        let mut nodes = vec![&root];
+        while let Some(node) = nodes.pop() {
+            if node.id == id {
+                return node.marks.iter().any(|mark| mark == POPUP_MARK);
+            }
+            nodes.extend(node.nodes.iter().chain(&node.floating_nodes).flatten());
+        }
+        false
+    })
+}
+
+/// Show the popup, hide it when it is focused, or spawn it when there is none
+pub(crate) fn toggle(settings: &Settings) -> Result<String> {
+    if palette::marks().iter().any(|mark| mark == POPUP_MARK) {
+        return i3_cmd(&[&criteria(), "scratchpad", "show"]);
+    }
+
+    let adopted = (!settings.popup_class.is_empty())
+        .then(|| {
+            list_windows()
+                .into_iter()
+                .find(|window| window.class_name().as_ref() == Some(&settings.popup_class))
+        })
+        .flatten();
+    match adopted {
+        Some(window) => i3_cmd(&[
+            &format!(r#"[con_id="{}"]"#, window.id),
+            "mark",
+            "--add",
+            &format!("{},", POPUP_MARK),
+            &show_commands(settings),
+        ]),
+        None => i3_cmd(&["exec", &spawn_command(settings)]),
+    }
+}
+
+/// Send the popup back to the scratchpad
+pub(crate) fn hide() -> Result<String> {
+    i3_cmd(&[&criteria(), "move", "scratchpad"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_marks_the_terminal() {
+        let settings = Settings {
+            terminal: "alacritty".to_string(),
+            popup_width: 60,
+            popup_height: 50,
+            ..Settings::default()
+        };
+        assert_eq!(
+            spawn_command(&settings),
+            "alacritty -e sh -c 'i3-msg \"[id=\\\"$WINDOWID\\\"] mark --add _i3mgr_popup, \
+             move scratchpad; [con_mark=\\\"^_i3mgr_popup$\\\"] scratchpad show, \
+             resize set 60 ppt 50 ppt, move position center\" >/dev/null; exec i3-mgr'"
+        );
+    }
+}
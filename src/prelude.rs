@@ -9,7 +9,7 @@ pub(crate) use std::u64;
 pub(crate) use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
-        event::{self, KeyCode, KeyEventKind},
+        event::{self, DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEventKind},
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
         ExecutableCommand,
     },
@@ -20,6 +20,6 @@ pub(crate) use ratatui::{
 };
 
 pub(crate) use ratatui::style::{
-    palette::tailwind::{BLUE, GREEN, SLATE},
+    palette::tailwind::{BLUE, GREEN, SLATE, YELLOW},
     Color, Modifier, Style,
 };
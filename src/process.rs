@@ -0,0 +1,251 @@
+//! Facilities to map i3 windows to the processes that own them.
+
+use crate::prelude::*;
+use crate::shutils::{cmd, i3_cmd, pipe};
+use crate::window::Window;
+use std::time::Instant;
+
+/// The different ways we can get rid of a window, from the most polite to the most brutal.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum KillMethod {
+    /// Ask the window to close itself (WM_DELETE_WINDOW)
+    Close,
+    /// Have i3 drop the X11 client connection
+    KillClient,
+    /// Send SIGTERM to the owning process
+    Terminate,
+    /// Send SIGKILL to the owning process
+    ForceKill,
+}
+
+impl KillMethod {
+    pub(crate) fn describe(&self) -> &'static str {
+        match self {
+            KillMethod::Close => "Closed",
+            KillMethod::KillClient => "Killed client",
+            KillMethod::Terminate => "Sent SIGTERM to",
+            KillMethod::ForceKill => "Sent SIGKILL to",
+        }
+    }
+}
+
+/// Retrieve the PID of the process owning an X11 window through its `_NET_WM_PID` property.
+pub(crate) fn window_pid(xid: u64) -> Option<u32> {
+    let output = pipe(&mut [&mut cmd(&[
+        "xprop",
+        "-id",
+        &format!("0x{:x}", xid),
+        "_NET_WM_PID",
+    ])])
+    .ok()?;
+
+    // Output looks like: `_NET_WM_PID(CARDINAL) = 12345`
+    output.split('=').nth(1)?.trim().parse::<u32>().ok()
+}
+
+/// Retrieve the host the client of an X11 window runs on through its `WM_CLIENT_MACHINE`
+/// property. Clients forwarded over `ssh -X` report a remote host, and PIDs of that host.
+pub(crate) fn window_machine(xid: u64) -> Option<String> {
+    let output = pipe(&mut [&mut cmd(&[
+        "xprop",
+        "-id",
+        &format!("0x{:x}", xid),
+        "WM_CLIENT_MACHINE",
+    ])])
+    .ok()?;
+
+    // Output looks like: `WM_CLIENT_MACHINE(STRING) = "myhost"`
+    let machine = output.split_once('=')?.1.trim().trim_matches('"');
+    Some(machine.to_string())
+}
+
+/// The name of the local host
+pub(crate) fn hostname() -> Option<String> {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|name| name.trim().to_string())
+}
+
+/// Send a signal to a process with the `kill` executable.
+pub(crate) fn signal(pid: u32, signal_name: &str) -> Result<String> {
+    let output = cmd(&["kill", &format!("-{}", signal_name), &pid.to_string()]).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("kill -{} {} failed: {}", signal_name, pid, stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Close the i3 container `con_id` (whose X11 window is `xid`) using the given method.
+pub(crate) fn kill_window(con_id: u64, xid: Option<u64>, method: KillMethod) -> Result<String> {
+    let criteria = format!(r#"[con_id="{}"]"#, con_id);
+    match method {
+        KillMethod::Close => i3_cmd(&[&criteria, "kill", "window"]),
+        KillMethod::KillClient => i3_cmd(&[&criteria, "kill", "client"]),
+        KillMethod::Terminate | KillMethod::ForceKill => {
+            let pid = xid
+                .and_then(window_pid)
+                .ok_or(format!("Unable to resolve the PID of container {}", con_id))?;
+            let signal_name = if method == KillMethod::Terminate {
+                "TERM"
+            } else {
+                "KILL"
+            };
+            signal(pid, signal_name)
+        }
+    }
+}
+
+/// Number of clock ticks per second used by the kernel to account cpu time.
+fn clock_ticks() -> u64 {
+    pipe(&mut [&mut cmd(&["getconf", "CLK_TCK"])])
+        .ok()
+        .and_then(|ticks| ticks.trim().parse::<u64>().ok())
+        .unwrap_or(100)
+}
+
+/// Total cpu time (user + system) consumed by a process, in clock ticks.
+fn cpu_ticks(pid: u32) -> Option<u64> {
+    parse_cpu_ticks(&std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?)
+}
+
+/// User plus system time out of the content of `/proc/<pid>/stat`.
+fn parse_cpu_ticks(stat: &str) -> Option<u64> {
+    // The command name is wrapped in parentheses and may itself contain spaces,
+    // so only start splitting after the closing one. Field 14 (utime) and 15 (stime)
+    // of `man proc` then land at index 11 and 12.
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime = fields.get(11)?.parse::<u64>().ok()?;
+    let stime = fields.get(12)?.parse::<u64>().ok()?;
+    Some(utime + stime)
+}
+
+/// Resident memory of a process in kB.
+fn rss_kb(pid: u32) -> Option<u64> {
+    parse_rss_kb(&std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?)
+}
+
+/// The `VmRSS` line out of the content of `/proc/<pid>/status`.
+fn parse_rss_kb(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find(|line| line.starts_with("VmRSS:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse::<u64>()
+        .ok()
+}
+
+/// A snapshot of the resources used by a single process.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ProcessUsage {
+    pub(crate) pid: u32,
+    pub(crate) cpu_percent: f64,
+    pub(crate) rss_kb: u64,
+}
+
+impl ProcessUsage {
+    pub(crate) fn usage_str(&self) -> String {
+        format!(
+            "{:5.1}% {:>7.1}M (pid {})",
+            self.cpu_percent,
+            self.rss_kb as f64 / 1024.0,
+            self.pid
+        )
+    }
+}
+
+/// Keep track of the processes behind our windows so that cpu usage can be computed
+/// between two consecutive refreshes.
+#[derive(Debug)]
+pub(crate) struct ProcessMonitor {
+    ticks_per_sec: u64,
+    /// X11 window id => PID
+    pids: HashMap<u64, u32>,
+    /// PID => (cpu ticks, time of the sample)
+    samples: HashMap<u32, (u64, Instant)>,
+    /// X11 window id => latest usage
+    usage: HashMap<u64, ProcessUsage>,
+}
+
+impl ProcessMonitor {
+    pub(crate) fn new() -> Self {
+        ProcessMonitor {
+            ticks_per_sec: clock_ticks(),
+            pids: HashMap::new(),
+            samples: HashMap::new(),
+            usage: HashMap::new(),
+        }
+    }
+
+    /// Resample the processes owning the given windows.
+    pub(crate) fn refresh(&mut self, windows: &[Window]) {
+        let now = Instant::now();
+        let mut usage = HashMap::new();
+        let mut samples = HashMap::new();
+
+        for xid in windows.iter().filter_map(|w| w.window) {
+            let pid = match self.pids.get(&xid) {
+                Some(pid) => Some(*pid),
+                None => window_pid(xid),
+            };
+            let Some(pid) = pid else { continue };
+            self.pids.insert(xid, pid);
+
+            let Some(ticks) = cpu_ticks(pid) else {
+                // The process is gone, forget about it.
+                self.pids.remove(&xid);
+                continue;
+            };
+
+            let cpu_percent = match self.samples.get(&pid) {
+                Some((prev_ticks, prev_time)) => {
+                    let elapsed = now.duration_since(*prev_time).as_secs_f64();
+                    if elapsed > 0.0 {
+                        let used = ticks.saturating_sub(*prev_ticks) as f64;
+                        100.0 * used / self.ticks_per_sec as f64 / elapsed
+                    } else {
+                        0.0
+                    }
+                }
+                None => 0.0,
+            };
+
+            samples.insert(pid, (ticks, now));
+            usage.insert(
+                xid,
+                ProcessUsage {
+                    pid,
+                    cpu_percent,
+                    rss_kb: rss_kb(pid).unwrap_or(0),
+                },
+            );
+        }
+
+        self.samples = samples;
+        self.usage = usage;
+    }
+
+    /// Retrieve the latest resource usage of the process owning a window.
+    pub(crate) fn usage(&self, window: &Window) -> Option<ProcessUsage> {
+        window.window.and_then(|xid| self.usage.get(&xid).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_proc_files() {
+        // The command name holds spaces and a parenthesis
+        let stat = "4242 (Web Content (1)) S 1 4242 4242 0 -1 4194560 51209 0 12 0 \
+                    1500 250 0 0 20 0 31 0 123456 3045597184 98304 18446744073709551615";
+        assert_eq!(parse_cpu_ticks(stat), Some(1750));
+        assert_eq!(parse_cpu_ticks("4242 (sh) S 1"), None);
+
+        let status = "Name:\tfirefox\nVmPeak:\t 3000000 kB\nVmRSS:\t  393216 kB\nThreads:\t31\n";
+        assert_eq!(parse_rss_kb(status), Some(393216));
+        assert_eq!(parse_rss_kb("Name:\tkthreadd\nThreads:\t1\n"), None);
+    }
+}
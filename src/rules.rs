@@ -0,0 +1,427 @@
+//! Rules applied to new windows, as a reloadable alternative to i3's `assign` and `for_window`.
+//!
+//! Rules are read from `rules.json` in the configuration directory:
+//!
+//! ```json
+//! [
+//!     {
+//!         "name": "browsers on 2",
+//!         "criteria": {"class": "(?i)firefox|chromium"},
+//!         "actions": [{"move_to_workspace": "2"}]
+//!     },
+//!     {
+//!         "name": "floating dialogs",
+//!         "criteria": {"role": "^pop-up$"},
+//!         "actions": [{"float": {"width": 800, "height": 600}}, {"mark": "dialog"}]
+//!     }
+//! ]
+//! ```
+//!
+//! Criteria are regular expressions that must all match. Only the first matching rule is applied.
+
+use crate::config;
+use crate::log;
+use crate::prelude::*;
+use crate::shutils::{i3_cmd, quoted};
+use crate::window::{list_workspace_trees, Window};
+use regex::Regex;
+use std::collections::VecDeque;
+
+pub(crate) const RULES_FILE: &str = "rules.json";
+const MAX_LOG_ENTRIES: usize = 100;
+
+/// Regular expressions a window has to match, absent ones match anything.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct Criteria {
+    pub(crate) class: Option<String>,
+    pub(crate) instance: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) role: Option<String>,
+    /// Name of the workspace the window was opened on
+    pub(crate) workspace: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RuleAction {
+    MoveToWorkspace(String),
+    Float {
+        width: Option<u64>,
+        height: Option<u64>,
+    },
+    Mark(String),
+    Scratchpad,
+    /// One of i3's layouts: default, tabbed, stacking, splitv or splith
+    Layout(String),
+}
+
+impl RuleAction {
+    fn command(&self) -> String {
+        match self {
+            RuleAction::MoveToWorkspace(workspace) => {
+                format!("move container to workspace {}", quoted(workspace))
+            }
+            RuleAction::Float { width, height } => match (width, height) {
+                (Some(width), Some(height)) => format!(
+                    "floating enable, resize set {} px {} px, move position center",
+                    width, height
+                ),
+                (Some(width), None) => format!(
+                    "floating enable, resize set width {} px, move position center",
+                    width
+                ),
+                (None, Some(height)) => format!(
+                    "floating enable, resize set height {} px, move position center",
+                    height
+                ),
+                (None, None) => "floating enable".to_string(),
+            },
+            RuleAction::Mark(mark) => format!("mark --add {}", quoted(mark)),
+            RuleAction::Scratchpad => "move scratchpad".to_string(),
+            RuleAction::Layout(layout) => format!("layout {}", layout),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Rule {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) criteria: Criteria,
+    pub(crate) actions: Vec<RuleAction>,
+}
+
+impl Rule {
+    /// One line summary of the rule for the Rules pane
+    pub(crate) fn summary(&self) -> String {
+        let criteria = [
+            ("class", &self.criteria.class),
+            ("instance", &self.criteria.instance),
+            ("title", &self.criteria.title),
+            ("role", &self.criteria.role),
+            ("workspace", &self.criteria.workspace),
+        ]
+        .iter()
+        .filter_map(|(name, value)| value.as_ref().map(|v| format!("{}={}", name, v)))
+        .collect::<Vec<String>>()
+        .join(" ");
+        let actions = self
+            .actions
+            .iter()
+            .map(|action| action.command())
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        format!("{}: [{}] => {}", self.name, criteria, actions)
+    }
+}
+
+/// A rule with its criteria compiled
+#[derive(Debug)]
+struct CompiledRule {
+    rule: Rule,
+    class: Option<Regex>,
+    instance: Option<Regex>,
+    title: Option<Regex>,
+    role: Option<Regex>,
+    workspace: Option<Regex>,
+}
+
+fn compile(pattern: &Option<String>) -> Result<Option<Regex>> {
+    Ok(match pattern {
+        Some(pattern) => Some(Regex::new(pattern)?),
+        None => None,
+    })
+}
+
+/// Whether an optional regex matches an optional value
+fn matches(regex: &Option<Regex>, value: Option<&str>) -> bool {
+    match regex {
+        None => true,
+        Some(regex) => value.is_some_and(|value| regex.is_match(value)),
+    }
+}
+
+impl CompiledRule {
+    fn new(rule: Rule) -> Result<Self> {
+        Ok(CompiledRule {
+            class: compile(&rule.criteria.class)?,
+            instance: compile(&rule.criteria.instance)?,
+            title: compile(&rule.criteria.title)?,
+            role: compile(&rule.criteria.role)?,
+            workspace: compile(&rule.criteria.workspace)?,
+            rule,
+        })
+    }
+
+    /// `workspace` is only computed when the rule actually needs it since it requires querying
+    /// the whole tree.
+    fn is_match(&self, window: &Window, workspace: &mut impl FnMut() -> Option<String>) -> bool {
+        let props = window.window_properties.clone().unwrap_or_default();
+        let title = window.name.clone().or(props.title);
+
+        matches(&self.class, window.class_name().as_deref())
+            && matches(&self.instance, props.instance.as_deref())
+            && matches(&self.title, title.as_deref())
+            && matches(&self.role, props.window_role.as_deref())
+            && (self.workspace.is_none() || matches(&self.workspace, workspace().as_deref()))
+    }
+}
+
+/// A line of the "which rule matched" log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RuleLogEntry {
+    pub(crate) time: String,
+    pub(crate) window: String,
+    /// Name of the matching rule, if any
+    pub(crate) rule: Option<String>,
+    /// i3 reply or error
+    pub(crate) outcome: String,
+}
+
+impl RuleLogEntry {
+    pub(crate) fn log_str(&self) -> String {
+        match &self.rule {
+            Some(rule) => format!(
+                "[{}] {} matched '{}': {}",
+                self.time, self.window, rule, self.outcome
+            ),
+            None => format!("[{}] {} {}", self.time, self.window, self.outcome),
+        }
+    }
+}
+
+/// What the Rules pane displays, possibly coming from the daemon
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct RulesSnapshot {
+    pub(crate) rules: Vec<Rule>,
+    pub(crate) log: Vec<RuleLogEntry>,
+}
+
+/// Loaded rules along with a log of what they did.
+#[derive(Debug, Default)]
+pub(crate) struct RulesEngine {
+    rules: Vec<CompiledRule>,
+    log: VecDeque<RuleLogEntry>,
+}
+
+impl RulesEngine {
+    /// Load the rules from the configuration directory, logging any error.
+    pub(crate) fn load() -> Self {
+        let mut engine = RulesEngine::default();
+        engine.reload();
+        engine
+    }
+
+    /// Read the rules file again. On error the previous rules are kept.
+    pub(crate) fn reload(&mut self) {
+        let outcome = config::load::<Vec<Rule>>(RULES_FILE).and_then(|rules| {
+            rules
+                .into_iter()
+                .map(CompiledRule::new)
+                .collect::<Result<Vec<CompiledRule>>>()
+        });
+
+        match outcome {
+            Ok(rules) => {
+                self.rules = rules;
+                self.push_log(None, "rules", format!("Loaded {} rules", self.rules.len()));
+            }
+            Err(err) => self.push_log(None, "rules", format!("Error: {}", err)),
+        }
+    }
+
+    /// Copy the rules and their log, most recent entries last
+    pub(crate) fn snapshot(&self) -> RulesSnapshot {
+        RulesSnapshot {
+            rules: self
+                .rules
+                .iter()
+                .map(|compiled| compiled.rule.clone())
+                .collect(),
+            log: self.log.iter().cloned().collect(),
+        }
+    }
+
+    fn push_log(&mut self, rule: Option<String>, window: &str, outcome: String) {
+        self.log.push_back(RuleLogEntry {
+            time: log::now(),
+            window: window.to_string(),
+            rule,
+            outcome,
+        });
+        while self.log.len() > MAX_LOG_ENTRIES {
+            self.log.pop_front();
+        }
+    }
+
+    /// Apply the first matching rule to a freshly opened window
+    pub(crate) fn handle_new_window(&mut self, window: &Window) {
+        let mut workspace_cache: Option<Option<String>> = None;
+        let mut workspace = || {
+            workspace_cache
+                .get_or_insert_with(|| workspace_of(window.id))
+                .clone()
+        };
+
+        let Some(compiled) = self
+            .rules
+            .iter()
+            .find(|compiled| compiled.is_match(window, &mut workspace))
+        else {
+            return;
+        };
+
+        let command = compiled
+            .rule
+            .actions
+            .iter()
+            .map(|action| action.command())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let outcome = match i3_cmd(&[&format!(r#"[con_id="{}"]"#, window.id), &command]) {
+            Ok(reply) => format!("{} => {}", command, reply.trim()),
+            Err(err) => format!("{} => Error: {}", command, err),
+        };
+
+        let rule_name = compiled.rule.name.clone();
+        let window_name = format!(
+            "[{}] {}",
+            window.class_name().unwrap_or_default(),
+            window.name.clone().unwrap_or_default()
+        );
+        self.push_log(Some(rule_name), &window_name, outcome);
+    }
+}
+
+/// Name of the workspace holding the container `con_id`
+fn workspace_of(con_id: u64) -> Option<String> {
+    list_workspace_trees()
+        .ok()?
+        .into_iter()
+        .find(|(_, tree)| tree.layout_leaves().iter().any(|(w, _)| w.id == con_id))
+        .map(|(name, _)| name)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::window::fixture;
+
+    fn window(class: &str, title: &str, role: Option<&str>) -> Window {
+        fixture::node(serde_json::json!({
+            "name": title, "id": 42, "window": 1234,
+            "window_properties": {"class": class, "instance": class.to_lowercase(), "window_role": role},
+        }))
+    }
+
+    fn compiled(json: serde_json::Value) -> CompiledRule {
+        CompiledRule::new(serde_json::from_value(json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parse_actions() {
+        let rule: Rule = serde_json::from_value(serde_json::json!({
+            "name": "dialogs",
+            "actions": [
+                {"move_to_workspace": "2"},
+                {"float": {"width": 800, "height": 600}},
+                {"mark": "dialog"},
+                "scratchpad",
+                {"layout": "tabbed"}
+            ]
+        }))
+        .unwrap();
+
+        let commands: Vec<String> = rule.actions.iter().map(|a| a.command()).collect();
+        assert_eq!(
+            commands,
+            vec![
+                "move container to workspace \"2\"",
+                "floating enable, resize set 800 px 600 px, move position center",
+                "mark --add \"dialog\"",
+                "move scratchpad",
+                "layout tabbed",
+            ]
+        );
+    }
+
+    #[test]
+    fn quote_names_and_keep_single_dimensions() {
+        let rule: Rule = serde_json::from_value(serde_json::json!({
+            "name": "injection",
+            "actions": [
+                {"move_to_workspace": "2, kill"},
+                {"mark": "a\"b"},
+                {"float": {"width": 800}},
+                {"float": {"height": 600}}
+            ]
+        }))
+        .unwrap();
+
+        let commands: Vec<String> = rule.actions.iter().map(|a| a.command()).collect();
+        assert_eq!(
+            commands,
+            vec![
+                "move container to workspace \"2, kill\"",
+                "mark --add \"a\\\"b\"",
+                "floating enable, resize set width 800 px, move position center",
+                "floating enable, resize set height 600 px, move position center",
+            ]
+        );
+    }
+
+    #[test]
+    fn match_criteria() {
+        let rule = compiled(serde_json::json!({
+            "name": "firefox dialogs",
+            "criteria": {"class": "(?i)^firefox$", "role": "pop-up"},
+            "actions": ["scratchpad"]
+        }));
+        let mut no_workspace = || None;
+
+        assert!(rule.is_match(
+            &window("Firefox", "Save as", Some("pop-up")),
+            &mut no_workspace
+        ));
+        assert!(!rule.is_match(&window("Firefox", "Mozilla", None), &mut no_workspace));
+        assert!(!rule.is_match(
+            &window("Chromium", "Save as", Some("pop-up")),
+            &mut no_workspace
+        ));
+    }
+
+    #[test]
+    fn workspace_only_queried_when_needed() {
+        let rule = compiled(serde_json::json!({
+            "name": "terminals",
+            "criteria": {"instance": "alacritty"},
+            "actions": [{"layout": "stacking"}]
+        }));
+        let mut queried = false;
+        let mut workspace = || {
+            queried = true;
+            Some("1".to_string())
+        };
+
+        assert!(rule.is_match(&window("Alacritty", "zsh", None), &mut workspace));
+        assert!(!queried);
+
+        let rule = compiled(serde_json::json!({
+            "name": "terminals on 3",
+            "criteria": {"workspace": "^3$"},
+            "actions": [{"layout": "stacking"}]
+        }));
+        assert!(
+            !rule.is_match(&window("Alacritty", "zsh", None), &mut || Some(
+                "1".to_string()
+            ))
+        );
+        assert!(
+            rule.is_match(&window("Alacritty", "zsh", None), &mut || Some(
+                "3".to_string()
+            ))
+        );
+    }
+}
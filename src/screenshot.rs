@@ -0,0 +1,257 @@
+//! Save the content of windows to image files, through `xwd`.
+//!
+//! The format follows the extension of the file: PNG, JPEG or WebP. The capture can be cropped
+//! (in the pixels of the window) then scaled.
+
+use crate::prelude::*;
+use crate::state;
+use crate::window::Window;
+use crate::x11window::get_window_image;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Directory under the state directory where the TUI writes its screenshots
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Longest file name derived from a window, extension excluded
+const MAX_NAME_LEN: usize = 60;
+
+/// Largest scale factor, beyond which the images only get huge
+const MAX_SCALE: f32 = 16.0;
+
+/// A rectangle in the pixels of the window, written `WxH+X+Y` like X geometries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Crop {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+}
+
+impl Crop {
+    pub(crate) fn parse(geometry: &str) -> Option<Self> {
+        let (size, position) = geometry.split_once('+').unwrap_or((geometry, "0+0"));
+        let (width, height) = size.split_once('x')?;
+        let (x, y) = position.split_once('+')?;
+        let crop = Crop {
+            width: width.parse().ok()?,
+            height: height.parse().ok()?,
+            x: x.parse().ok()?,
+            y: y.parse().ok()?,
+        };
+        (crop.width > 0 && crop.height > 0).then_some(crop)
+    }
+}
+
+/// A scale factor in (0, 16], e.g. `0.5`
+pub(crate) fn parse_scale(scale: &str) -> Option<f32> {
+    scale
+        .parse()
+        .ok()
+        .filter(|scale: &f32| scale.is_finite() && *scale > 0.0 && *scale <= MAX_SCALE)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ScreenshotOptions {
+    pub(crate) crop: Option<Crop>,
+    /// e.g. 0.5 for half the size
+    pub(crate) scale: Option<f32>,
+}
+
+impl ScreenshotOptions {
+    fn transform(&self, mut image: DynamicImage) -> Result<DynamicImage> {
+        if let Some(crop) = self.crop {
+            // crop_imm would silently clamp the rectangle, down to nothing at all
+            if crop.x as u64 + crop.width as u64 > image.width() as u64
+                || crop.y as u64 + crop.height as u64 > image.height() as u64
+            {
+                return Err(format!(
+                    "Crop {}x{}+{}+{} exceeds the {}x{} window",
+                    crop.width,
+                    crop.height,
+                    crop.x,
+                    crop.y,
+                    image.width(),
+                    image.height()
+                )
+                .into());
+            }
+            image = image.crop_imm(crop.x, crop.y, crop.width, crop.height);
+        }
+        if let Some(scale) = self.scale {
+            let width = ((image.width() as f32 * scale).round() as u32).max(1);
+            let height = ((image.height() as f32 * scale).round() as u32).max(1);
+            image = image.resize_exact(width, height, FilterType::Triangle);
+        }
+        Ok(image)
+    }
+}
+
+/// The image format matching the extension of `path`
+fn format_of(path: &Path) -> Result<ImageFormat> {
+    match ImageFormat::from_path(path) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP)) => Ok(format),
+        _ => Err(format!("{}: expected a .png, .jpg or .webp file", path.display()).into()),
+    }
+}
+
+/// Check an extension given on its own, e.g. `webp`
+pub(crate) fn check_extension(extension: &str) -> Result<()> {
+    format_of(Path::new(&format!("shot.{}", extension)))
+        .map(|_| ())
+        .map_err(|_| {
+            format!(
+                "Unsupported format '{}', expected png, jpg or webp",
+                extension
+            )
+            .into()
+        })
+}
+
+/// A file name made of the class and title of the window, e.g. `firefox-mozilla-firefox`
+pub(crate) fn file_stem(window: &Window) -> String {
+    let source = format!(
+        "{} {}",
+        window.class_name().unwrap_or_default(),
+        window.name.clone().unwrap_or_default()
+    );
+    let mut stem = String::new();
+    for c in source.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            stem.push(c);
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
+        }
+    }
+    let stem: String = stem
+        .trim_end_matches('-')
+        .chars()
+        .take(MAX_NAME_LEN)
+        .collect();
+    match stem.trim_end_matches('-') {
+        "" => format!("window-{}", window.id),
+        stem => stem.to_string(),
+    }
+}
+
+/// Capture `window` into `path`
+pub(crate) fn screenshot_window(
+    window: &Window,
+    path: &Path,
+    options: &ScreenshotOptions,
+) -> Result<()> {
+    let format = format_of(path)?;
+    let xid = window
+        .window
+        .ok_or_else(|| format!("Container {} holds no X11 window", window.id))?;
+    let image = options.transform(get_window_image(xid)?)?;
+
+    // JPEG has no alpha channel
+    let image = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()),
+        _ => image,
+    };
+    image.save_with_format(path, format)?;
+    Ok(())
+}
+
+/// Capture every window into `dir`, one file per window named after its class and title.
+/// Returns the files written and the windows that couldn't be captured.
+pub(crate) fn screenshot_windows(
+    windows: &[Window],
+    dir: &Path,
+    extension: &str,
+    options: &ScreenshotOptions,
+) -> Result<(Vec<PathBuf>, Vec<String>)> {
+    std::fs::create_dir_all(dir)?;
+    let mut stems = HashSet::new();
+    let mut written = vec![];
+    let mut failed = vec![];
+    for window in windows {
+        let mut stem = file_stem(window);
+        let mut count = 1;
+        while !stems.insert(stem.clone()) {
+            count += 1;
+            stem = format!("{}-{}", file_stem(window), count);
+        }
+
+        let path = dir.join(format!("{}.{}", stem, extension));
+        match screenshot_window(window, &path, options) {
+            Ok(()) => written.push(path),
+            Err(err) => failed.push(format!("{}: {}", window.name_str(), err)),
+        }
+    }
+    Ok((written, failed))
+}
+
+/// Where the TUI writes its screenshots
+pub(crate) fn screenshot_dir() -> PathBuf {
+    state::state_file(SCREENSHOT_DIR)
+}
+
+/// The first window whose con id is `query`, or whose class or title contains it
+pub(crate) fn find_window<'a>(windows: &'a [Window], query: &str) -> Option<&'a Window> {
+    let query_lower = query.to_lowercase();
+    windows
+        .iter()
+        .find(|window| window.id.to_string() == query)
+        .or_else(|| {
+            windows.iter().find(|window| {
+                let class = window.class_name().unwrap_or_default().to_lowercase();
+                let title = window.name.clone().unwrap_or_default().to_lowercase();
+                class.contains(&query_lower) || title.contains(&query_lower)
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::fixture;
+
+    fn window(id: u64, class: &str, title: &str) -> Window {
+        fixture::window(id, Some(class), title)
+    }
+
+    #[test]
+    fn name_and_transform_captures() {
+        let firefox = window(7, "firefox", "Rust — Mozilla Firefox");
+        assert_eq!(file_stem(&firefox), "firefox-rust-mozilla-firefox");
+        assert_eq!(file_stem(&window(8, "", "…")), "window-8");
+        let windows = [window(3, "Alacritty", "vim"), firefox];
+        assert_eq!(find_window(&windows, "7").map(|w| w.id), Some(7));
+        assert_eq!(find_window(&windows, "ALAC").map(|w| w.id), Some(3));
+
+        assert_eq!(
+            Crop::parse("200x100+10+20"),
+            Some(Crop {
+                width: 200,
+                height: 100,
+                x: 10,
+                y: 20
+            })
+        );
+        assert_eq!(Crop::parse("200x100").map(|crop| crop.x), Some(0));
+        assert_eq!(Crop::parse("200"), None);
+        assert_eq!(Crop::parse("0x100+0+0"), None);
+        assert_eq!(parse_scale("0.5"), Some(0.5));
+        assert_eq!(parse_scale("NaN"), None);
+        assert_eq!(parse_scale("0"), None);
+        assert_eq!(parse_scale("17"), None);
+
+        let options = ScreenshotOptions {
+            crop: Crop::parse("200x100+10+20"),
+            scale: Some(0.5),
+        };
+        let image = options.transform(DynamicImage::new_rgb8(400, 300)).unwrap();
+        assert_eq!((image.width(), image.height()), (100, 50));
+        assert!(options.transform(DynamicImage::new_rgb8(200, 100)).is_err());
+
+        assert!(format_of(Path::new("shot.webp")).is_ok());
+        assert!(format_of(Path::new("shot.gif")).is_err());
+        assert!(check_extension("jpg").is_ok());
+        assert!(check_extension("gif").is_err());
+    }
+}
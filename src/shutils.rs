@@ -2,6 +2,7 @@
 ///
 ///
 ///
+use crate::log::{self, Level};
 use crate::prelude::Result;
 use std::process as proc;
 use std::process::Stdio;
@@ -19,7 +20,17 @@ pub(crate) fn cmd(args: &[&str]) -> proc::Command {
 pub(crate) fn i3_cmd(cmd_strings: &[&str]) -> Result<String> {
     let mut cmds = vec!["i3-msg"];
     cmds.extend(cmd_strings);
-    pipe(&mut [&mut cmd(cmds.as_slice())])
+    log::record(Level::Debug, cmds.join(" "));
+
+    let reply = pipe(&mut [&mut cmd(cmds.as_slice())]);
+    match &reply {
+        Ok(reply) if reply.contains(r#""success":false"#) => {
+            log::record(Level::Error, format!("i3 replied: {}", reply.trim()))
+        }
+        Ok(reply) => log::record(Level::Debug, format!("i3 replied: {}", reply.trim())),
+        Err(err) => log::record(Level::Error, format!("i3-msg failed: {}", err)),
+    }
+    reply
 }
 
 /// Create a chain of commands that are piped together and extract the std out.
@@ -44,6 +55,62 @@ pub(crate) fn move_window_to_workspace(window_id: u64, target_workspace: &str) -
         &format!(r#"[con_id="{}"]"#, window_id),
         "move",
         "workspace",
-        target_workspace,
+        &quoted(target_workspace),
     ])
 }
+
+/// Quote a workspace name or mark for an i3 command, escaping the quotes and backslashes in it
+pub(crate) fn quoted(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Mark used to temporarily tag the container a window is dropped next to.
+const DROP_MARK: &str = "_i3mgr_drop";
+
+/// Move a window right next to (before or after) another container, possibly on another workspace.
+pub(crate) fn move_window_next_to(window_id: u64, anchor_id: u64, before: bool) -> Result<String> {
+    i3_cmd(&[
+        &format!(r#"[con_id="{}"]"#, anchor_id),
+        "mark",
+        "--add",
+        DROP_MARK,
+    ])?;
+
+    // Moving to a mark always places the window after the marked container
+    let moved = i3_cmd(&[
+        &format!(r#"[con_id="{}"]"#, window_id),
+        "move",
+        "container",
+        "to",
+        "mark",
+        DROP_MARK,
+    ])
+    .and_then(|reply| {
+        if !before {
+            return Ok(reply);
+        }
+        i3_cmd(&[
+            &format!(r#"[con_id="{}"]"#, window_id),
+            "swap",
+            "container",
+            "with",
+            "con_id",
+            &anchor_id.to_string(),
+        ])
+    });
+
+    // The mark goes away whether the move worked or not
+    i3_cmd(&["unmark", DROP_MARK])?;
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_names() {
+        assert_eq!(quoted("2: web"), r#""2: web""#);
+        assert_eq!(quoted(r#"3: "x" \ y"#), r#""3: \"x\" \\ y""#);
+    }
+}
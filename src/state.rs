@@ -0,0 +1,36 @@
+//! Persist small pieces of i3-mgr state between sessions.
+//!
+//! Everything lives as JSON files under `$XDG_STATE_HOME/i3-mgr` (or `~/.local/state/i3-mgr`).
+
+use crate::prelude::*;
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+
+/// Directory holding all of our state files
+pub(crate) fn state_dir() -> PathBuf {
+    let base = match std::env::var("XDG_STATE_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".local/state"),
+    };
+    base.join("i3-mgr")
+}
+
+/// Full path of a state file
+pub(crate) fn state_file(name: &str) -> PathBuf {
+    state_dir().join(name)
+}
+
+/// Load a state file, falling back to the default value when it is missing or unreadable.
+pub(crate) fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    std::fs::read_to_string(state_file(name))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Write a state file, creating the state directory if needed.
+pub(crate) fn save<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    std::fs::create_dir_all(state_dir())?;
+    std::fs::write(state_file(name), serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
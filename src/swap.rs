@@ -0,0 +1,139 @@
+//! Swap the contents of two workspaces, or two windows wherever they are.
+//!
+//! The top-level containers of both workspaces get a temporary mark first, so that each side
+//! can be moved with a single command and keep its order, split containers and floating
+//! windows included. The layout of each workspace follows its windows.
+
+use crate::prelude::*;
+use crate::shutils::{i3_cmd, quoted};
+use crate::window::{list_workspace_trees, ContainerCommand, Snapshot, Window};
+
+/// Prefix of the temporary marks, followed by the side (`a` or `b`) and a counter
+const SWAP_MARK: &str = "_i3mgr_swap";
+
+/// The con ids of the direct children of a workspace, tiled then floating
+fn top_level(workspace: &Window) -> Vec<u64> {
+    let tiled = workspace.nodes.iter().flatten();
+    let floating = workspace.floating_nodes.iter().flatten();
+    tiled.chain(floating).map(|node| node.id).collect()
+}
+
+/// The i3 commands exchanging the contents of the workspace trees `a` and `b`, each side
+/// taking the layout of the workspace it came from along. The last one drops the temporary
+/// marks and has to run whatever happened to the others.
+fn swap_commands(a: (&str, &Window), b: (&str, &Window)) -> (Vec<String>, String) {
+    let mut marks = vec![];
+    let mut moves = vec![];
+    let mut layouts = vec![];
+    let mut unmarks = vec![];
+    for (side, (_, tree), (destination, _)) in [("a", a, b), ("b", b, a)] {
+        let ids = top_level(tree);
+        for (index, id) in ids.iter().enumerate() {
+            let mark = format!("{}_{}_{}", SWAP_MARK, side, index);
+            marks.push(format!(r#"[con_id="{}"] mark --add {}"#, id, mark));
+            unmarks.push(format!("unmark {}", mark));
+        }
+        if ids.is_empty() {
+            continue;
+        }
+        moves.push(format!(
+            r#"[con_mark="^{}_{}_"] move container to workspace {}"#,
+            SWAP_MARK,
+            side,
+            quoted(destination)
+        ));
+        // Applied to a child of the workspace, `layout` changes the workspace itself
+        if let Some(layout) = &tree.layout {
+            layouts.push(format!(
+                r#"[con_mark="^{}_{}_0$"] layout {}"#,
+                SWAP_MARK, side, layout
+            ));
+        }
+    }
+    let commands = vec![marks.join("; "), moves.join("; "), layouts.join("; ")]
+        .into_iter()
+        .filter(|command| !command.is_empty())
+        .collect();
+    (commands, unmarks.join("; "))
+}
+
+/// Exchange all the windows of workspaces `a` and `b`
+pub(crate) fn swap_workspaces(a: &str, b: &str) -> Result<()> {
+    if a == b {
+        return Err(format!("Can't swap workspace {} with itself", a).into());
+    }
+    let trees = list_workspace_trees()?;
+    let tree = |name: &str| -> Result<&Window> {
+        trees
+            .get(name)
+            .ok_or_else(|| format!("No workspace named {}", name).into())
+    };
+    let (commands, unmark) = swap_commands((a, tree(a)?), (b, tree(b)?));
+
+    let outcome = commands
+        .iter()
+        .try_for_each(|command| i3_cmd(&[command]).map(|_| ()));
+    if !unmark.is_empty() {
+        i3_cmd(&[&unmark])?;
+    }
+    outcome
+}
+
+/// Exchange the positions of two windows, even on different workspaces
+pub(crate) fn swap_windows(a: u64, b: u64) -> Result<String> {
+    if a == b {
+        return Err(format!("Can't swap window {} with itself", a).into());
+    }
+    let windows = Snapshot::collect()?.windows;
+    let window = |id: u64| -> Result<&Window> {
+        windows
+            .iter()
+            .find(|window| window.id == id)
+            .ok_or_else(|| format!("No container with con id {}", id).into())
+    };
+    window(b)?;
+    window(a)?.apply(&ContainerCommand::SwapWith(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::fixture;
+
+    fn workspace(layout: &str, ids: &[u64]) -> Window {
+        let windows = ids.iter().map(|id| fixture::window(*id, None, "w"));
+        fixture::workspace(layout, windows.collect())
+    }
+
+    #[test]
+    fn swap_marks_both_sides() {
+        let (commands, unmark) = swap_commands(
+            ("1", &workspace("splith", &[10, 11])),
+            ("2: web", &workspace("tabbed", &[20])),
+        );
+        assert_eq!(
+            commands,
+            vec![
+                "[con_id=\"10\"] mark --add _i3mgr_swap_a_0; \
+                 [con_id=\"11\"] mark --add _i3mgr_swap_a_1; \
+                 [con_id=\"20\"] mark --add _i3mgr_swap_b_0",
+                "[con_mark=\"^_i3mgr_swap_a_\"] move container to workspace \"2: web\"; \
+                 [con_mark=\"^_i3mgr_swap_b_\"] move container to workspace \"1\"",
+                "[con_mark=\"^_i3mgr_swap_a_0$\"] layout splith; \
+                 [con_mark=\"^_i3mgr_swap_b_0$\"] layout tabbed",
+            ]
+        );
+        assert_eq!(
+            unmark,
+            "unmark _i3mgr_swap_a_0; unmark _i3mgr_swap_a_1; unmark _i3mgr_swap_b_0"
+        );
+
+        // Moving to an empty workspace
+        let (commands, _) = swap_commands(
+            ("1", &workspace("splith", &[10])),
+            ("3", &workspace("splitv", &[])),
+        );
+        assert_eq!(commands[1].matches("move").count(), 1);
+        assert_eq!(commands[2].matches("layout").count(), 1);
+    }
+}
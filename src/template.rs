@@ -0,0 +1,190 @@
+//! Workspace templates: named sets of workspaces whose layout is restored with `append_layout`
+//! before launching the applications that get swallowed into it.
+//!
+//! Templates are read from `templates.json` in the configuration directory:
+//!
+//! ```json
+//! {
+//!     "dev": [
+//!         {
+//!             "workspace": "1",
+//!             "layout": {
+//!                 "layout": "splith",
+//!                 "children": [
+//!                     {"command": "code", "class": "^Code$", "percent": 0.6},
+//!                     {
+//!                         "layout": "splitv",
+//!                         "children": [{"command": "alacritty"}, {"command": "alacritty"}]
+//!                     }
+//!                 ]
+//!             }
+//!         },
+//!         {"workspace": "2", "layout": {"command": "firefox"}}
+//!     ]
+//! }
+//! ```
+//!
+//! Nodes with a `command` are applications, the others are containers. Applications are
+//! swallowed by `class`, `instance` or `title` (regular expressions); without any of them we fall
+//! back on a case insensitive match of the class against the command name.
+
+use crate::config;
+use crate::prelude::*;
+use crate::shutils::{i3_cmd, quoted};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+pub(crate) const TEMPLATES_FILE: &str = "templates.json";
+
+/// Either a container (with `layout` and `children`) or an application (with `command`)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct TemplateNode {
+    /// One of splith, splitv, tabbed or stacking
+    pub(crate) layout: Option<String>,
+    #[serde(default)]
+    pub(crate) children: Vec<TemplateNode>,
+    /// Share of the parent container, between 0 and 1
+    pub(crate) percent: Option<f64>,
+    pub(crate) command: Option<String>,
+    pub(crate) class: Option<String>,
+    pub(crate) instance: Option<String>,
+    pub(crate) title: Option<String>,
+}
+
+impl TemplateNode {
+    /// Convert this node to the format expected by i3's `append_layout`
+    fn to_i3_layout(&self) -> Value {
+        let mut node = json!({"type": "con"});
+        if let Some(percent) = self.percent {
+            node["percent"] = json!(percent);
+        }
+
+        match &self.command {
+            Some(command) => {
+                let mut swallows = serde_json::Map::new();
+                for (key, value) in [
+                    ("class", &self.class),
+                    ("instance", &self.instance),
+                    ("title", &self.title),
+                ] {
+                    if let Some(value) = value {
+                        swallows.insert(key.to_string(), json!(value));
+                    }
+                }
+                if swallows.is_empty() {
+                    swallows.insert("class".to_string(), json!(default_class(command)));
+                }
+                node["swallows"] = json!([swallows]);
+            }
+            None => {
+                node["layout"] = json!(self.layout.clone().unwrap_or("splith".to_string()));
+                node["nodes"] = json!(self
+                    .children
+                    .iter()
+                    .map(|child| child.to_i3_layout())
+                    .collect::<Vec<Value>>());
+            }
+        }
+
+        node
+    }
+
+    /// Commands of the applications below this node, in tree order
+    fn commands(&self) -> Vec<String> {
+        match &self.command {
+            Some(command) => vec![command.clone()],
+            None => self.children.iter().flat_map(|c| c.commands()).collect(),
+        }
+    }
+}
+
+/// Swallow criterion guessed from a command, e.g. `(?i)^firefox$` for `/usr/bin/firefox --new`
+fn default_class(command: &str) -> String {
+    let program = command.split_whitespace().next().unwrap_or_default();
+    let name = program.rsplit('/').next().unwrap_or(program);
+    format!("(?i)^{}$", regex::escape(name))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct WorkspaceTemplate {
+    pub(crate) workspace: String,
+    pub(crate) layout: TemplateNode,
+}
+
+/// All the templates of the configuration, sorted by name
+pub(crate) fn load_templates() -> Result<BTreeMap<String, Vec<WorkspaceTemplate>>> {
+    config::load(TEMPLATES_FILE)
+}
+
+/// Create the workspaces of the template `name`, lay them out and launch their applications.
+pub(crate) fn apply_template(name: &str) -> Result<()> {
+    let templates = load_templates()?;
+    let workspaces = templates
+        .get(name)
+        .ok_or(format!("Unknown template '{}'", name))?;
+
+    for (index, workspace) in workspaces.iter().enumerate() {
+        i3_cmd(&["workspace", &quoted(&workspace.workspace)])?;
+
+        // Template names are free-form, keep them out of the path
+        let layout_file = std::env::temp_dir().join(format!(
+            "i3-mgr-layout-{}-{}.json",
+            std::process::id(),
+            index
+        ));
+        std::fs::write(
+            &layout_file,
+            serde_json::to_string_pretty(&workspace.layout.to_i3_layout())?,
+        )?;
+        let reply = i3_cmd(&["append_layout", &layout_file.to_string_lossy()]);
+        std::fs::remove_file(&layout_file)?;
+        reply?;
+
+        for command in workspace.layout.commands() {
+            i3_cmd(&["exec", "--no-startup-id", &command])?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_class_of_commands() {
+        assert_eq!(default_class("/usr/bin/firefox --new"), "(?i)^firefox$");
+        assert_eq!(default_class("emacs-29.1 -nw"), r"(?i)^emacs\-29\.1$");
+        assert_eq!(default_class("c++filt"), r"(?i)^c\+\+filt$");
+    }
+
+    #[test]
+    fn template_to_i3_layout() {
+        let template: TemplateNode = serde_json::from_value(json!({
+            "layout": "splith",
+            "children": [
+                {"command": "code", "class": "^Code$", "percent": 0.6},
+                {"layout": "tabbed", "children": [{"command": "alacritty -e htop"}]},
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            template.to_i3_layout(),
+            json!({
+                "type": "con",
+                "layout": "splith",
+                "nodes": [
+                    {"type": "con", "percent": 0.6, "swallows": [{"class": "^Code$"}]},
+                    {
+                        "type": "con",
+                        "layout": "tabbed",
+                        "nodes": [{"type": "con", "swallows": [{"class": "(?i)^alacritty$"}]}],
+                    },
+                ],
+            })
+        );
+        assert_eq!(template.commands(), ["code", "alacritty -e htop"]);
+    }
+}
@@ -3,10 +3,131 @@
 use std::process::ChildStderr;
 
 use crate::prelude::*;
-use crate::shutils::{cmd, i3_cmd, pipe};
+use crate::process::{self, KillMethod};
+use crate::shutils::{self, cmd, i3_cmd, pipe};
 use crate::workspace::Workspace;
 
-pub(crate) const WINDOW_PROPERTIES_SECTION: &str = r#"{name, id, type, "class": .window_properties.class, focused, output, sticky, floating, nodes, window, scratchpad_state}"#;
+pub(crate) const WINDOW_PROPERTIES_SECTION: &str = r#"{name, id, type, "class": .window_properties.class, window_properties, focused, output, sticky, floating, nodes, floating_nodes, window, window_type, scratchpad_state, rect, layout}"#;
+
+/// Directions a container can be moved in within the tree.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum MoveDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl MoveDirection {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MoveDirection::Left => "left",
+            MoveDirection::Right => "right",
+            MoveDirection::Up => "up",
+            MoveDirection::Down => "down",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Dimension {
+    Width,
+    Height,
+}
+
+/// Layout manipulation commands that can be applied to a single container.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum ContainerCommand {
+    SplitHorizontal,
+    SplitVertical,
+    LayoutTabbed,
+    LayoutStacking,
+    LayoutSplitH,
+    LayoutSplitV,
+    LayoutToggleSplit,
+    ToggleFullscreen,
+    ToggleSticky,
+    ToggleFloating,
+    Move(MoveDirection),
+    /// Grow or shrink a dimension by `ppt` percentage points
+    Resize {
+        grow: bool,
+        dimension: Dimension,
+        ppt: u32,
+    },
+    /// Swap with the container of the given con_id
+    SwapWith(u64),
+}
+
+impl ContainerCommand {
+    /// The i3 command (without criteria) that implements this action.
+    pub(crate) fn command(&self) -> String {
+        match self {
+            ContainerCommand::SplitHorizontal => "split horizontal".to_string(),
+            ContainerCommand::SplitVertical => "split vertical".to_string(),
+            ContainerCommand::LayoutTabbed => "layout tabbed".to_string(),
+            ContainerCommand::LayoutStacking => "layout stacking".to_string(),
+            ContainerCommand::LayoutSplitH => "layout splith".to_string(),
+            ContainerCommand::LayoutSplitV => "layout splitv".to_string(),
+            ContainerCommand::LayoutToggleSplit => "layout toggle split".to_string(),
+            ContainerCommand::ToggleFullscreen => "fullscreen toggle".to_string(),
+            ContainerCommand::ToggleSticky => "sticky toggle".to_string(),
+            ContainerCommand::ToggleFloating => "floating toggle".to_string(),
+            ContainerCommand::Move(direction) => format!("move {}", direction.as_str()),
+            ContainerCommand::Resize {
+                grow,
+                dimension,
+                ppt,
+            } => format!(
+                "resize {} {} {} px or {} ppt",
+                if *grow { "grow" } else { "shrink" },
+                match dimension {
+                    Dimension::Width => "width",
+                    Dimension::Height => "height",
+                },
+                ppt,
+                ppt
+            ),
+            ContainerCommand::SwapWith(con_id) => {
+                format!("swap container with con_id {}", con_id)
+            }
+        }
+    }
+}
+
+/// What a node of the i3 tree stands for
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum NodeKind {
+    Root,
+    Output,
+    Workspace,
+    /// The area of an output reserved for docks and bars
+    DockArea,
+    /// A split, tabbed or stacked container, or the wrapper of a floating window
+    Container,
+    /// An X11 window that can be focused, moved around, killed...
+    Window,
+    /// A dock or bar window, e.g. i3bar
+    Dock,
+}
+
+/// Position and size of a container, in pixels relative to the root window.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub(crate) struct Geometry {
+    pub(crate) x: i64,
+    pub(crate) y: i64,
+    pub(crate) width: u64,
+    pub(crate) height: u64,
+}
+
+/// X11 properties of the window held by a container.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct WindowProperties {
+    pub(crate) class: Option<String>,
+    pub(crate) instance: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) window_role: Option<String>,
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct Window {
@@ -19,10 +140,21 @@ pub(crate) struct Window {
     #[serde(rename = "type")]
     type_: Option<String>,
     pub(crate) nodes: Option<Vec<Window>>,
+    pub(crate) floating_nodes: Option<Vec<Window>>,
     pub(crate) focused: bool,
     pub(crate) class: Option<String>,
+    /// Only nested nodes rely on this, the top level ones get `class` pulled out by jq
+    pub(crate) window_properties: Option<WindowProperties>,
     floating: String,
     pub(crate) scratchpad_state: Option<String>,
+    pub(crate) rect: Option<Geometry>,
+    /// One of splith, splitv, stacked, tabbed, dockarea or output
+    pub(crate) layout: Option<String>,
+    /// The EWMH type of the X11 window, e.g. normal, dialog or dock
+    pub(crate) window_type: Option<String>,
+    /// Only filled in from a raw `get_tree`, jq leaves them out
+    #[serde(default)]
+    pub(crate) marks: Vec<String>,
 }
 
 impl Window {
@@ -33,7 +165,7 @@ impl Window {
     pub(crate) fn name_str(&self) -> String {
         format!(
             "[{:15}] {:20} <{}>",
-            self.class.as_ref().unwrap_or(&"".to_string()),
+            self.class_name().unwrap_or_default(),
             self.name.as_ref().unwrap_or(&"".to_string()),
             self.scratchpad_state
                 .as_ref()
@@ -41,24 +173,103 @@ impl Window {
         )
     }
 
-    /// Return true if this window is some container type like root, content, or workspace
-    pub(crate) fn is_container(&self) -> bool {
-        // Drop windows that aren't containers
-        if self.type_.is_none() {
-            true
+    /// Classify this node. Containers can have a name (tabbed and stacked ones get one, and
+    /// so does `content`) while real windows can lack one, so only the X11 window id and the
+    /// node and window types are trusted.
+    pub(crate) fn kind(&self) -> NodeKind {
+        match self.container_type() {
+            "root" => NodeKind::Root,
+            "output" => NodeKind::Output,
+            "workspace" => NodeKind::Workspace,
+            "dockarea" => NodeKind::DockArea,
+            _ if self.window.is_none() => NodeKind::Container,
+            _ if self.window_type.as_deref() == Some("dock") => NodeKind::Dock,
+            _ => NodeKind::Window,
+        }
+    }
+
+    /// Return true if this is a real window, neither a container nor a dock
+    pub(crate) fn is_window(&self) -> bool {
+        self.kind() == NodeKind::Window
+    }
+
+    /// One of root, output, con, floating_con, workspace or dockarea
+    pub(crate) fn container_type(&self) -> &str {
+        self.type_.as_deref().unwrap_or_default()
+    }
+
+    /// The X11 class of this window, whether or not jq extracted it.
+    pub(crate) fn class_name(&self) -> Option<String> {
+        self.class.clone().or_else(|| {
+            self.window_properties
+                .as_ref()
+                .and_then(|props| props.class.clone())
+        })
+    }
+
+    /// Short description: the class and title of a window, the type, layout and name of any
+    /// other container.
+    pub(crate) fn describe(&self) -> String {
+        match self.window {
+            Some(_) => format!(
+                "{}: {}",
+                self.class_name().unwrap_or_default(),
+                self.name.clone().unwrap_or_default()
+            ),
+            None => [
+                self.container_type(),
+                self.layout.as_deref().unwrap_or_default(),
+                self.name.as_deref().unwrap_or_default(),
+            ]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" "),
+        }
+    }
+
+    /// Describe this container and everything below it, one line per container indented by
+    /// its depth in the tree.
+    pub(crate) fn tree_lines(&self, depth: usize, lines: &mut Vec<String>) {
+        let floating = if self.is_floating() {
+            " [floating]"
         } else {
-            let type_name = self.type_.as_ref().unwrap();
-            if type_name == "con" {
-                match &self.name {
-                    None => true,
-                    Some(name) => name == "content",
-                }
-            } else {
-                true
-            }
+            ""
+        };
+        lines.push(format!(
+            "{}{}{} #{}",
+            "  ".repeat(depth),
+            self.describe(),
+            floating,
+            self.id
+        ));
+
+        for node in self.nodes.iter().chain(&self.floating_nodes).flatten() {
+            node.tree_lines(depth + 1, lines);
         }
     }
 
+    /// Collect all the actual X11 windows below this container, flagging the floating ones.
+    pub(crate) fn layout_leaves(&self) -> Vec<(Window, bool)> {
+        let tiled = self
+            .nodes
+            .iter()
+            .flatten()
+            .flat_map(|node| node.flatten())
+            .map(|w| (w, false));
+        let floating = self
+            .floating_nodes
+            .iter()
+            .flatten()
+            .flat_map(|node| node.flatten())
+            .map(|w| (w, true));
+
+        tiled
+            .chain(floating)
+            .filter(|(w, _)| w.is_window())
+            .collect()
+    }
+
     fn has_children(&self) -> bool {
         self.nodes.as_ref().map_or(false, |nodes| nodes.len() != 0)
     }
@@ -81,8 +292,14 @@ impl Window {
         i3_cmd(&[&format!(r#"[con_id="{}"]"#, self.id), "focus"])
     }
 
-    pub(crate) fn kill(&self) -> Result<String> {
-        i3_cmd(&[&format!(r#"[con_id="{}"]"#, self.id), "kill"])
+    /// Get rid of this window, either through i3 or by signaling the owning process.
+    pub(crate) fn kill_with(&self, method: KillMethod) -> Result<String> {
+        process::kill_window(self.id, self.window, method)
+    }
+
+    /// Run a layout manipulation command on this container.
+    pub(crate) fn apply(&self, command: &ContainerCommand) -> Result<String> {
+        i3_cmd(&[&format!(r#"[con_id="{}"]"#, self.id), &command.command()])
     }
 
     fn flatten_window(&self, children: &mut Vec<Window>) {
@@ -106,21 +323,55 @@ impl Window {
 
     /// Retrieve a list of all the window names that are a part of this window's nodes.
     fn node_names_(&self, v: &mut Vec<String>) {
-        if self.has_children() {
-            match &self.nodes {
-                Some(nodes) => {
-                    for node in nodes {
-                        node.node_names_(v);
-                    }
-                }
-                None => self.push_name_(v),
-            };
-        } else {
-            self.push_name_(v)
-        };
+        if self.is_window() {
+            self.push_name_(v);
+        }
+        for node in self.nodes.iter().flatten() {
+            node.node_names_(v);
+        }
+    }
+}
+
+/// Nodes shaped like the ones of `get_tree`, for the tests
+#[cfg(test)]
+pub(crate) mod fixture {
+    use super::Window;
+    use serde_json::{json, Value};
+
+    /// A tiled container, with `fields` added to or replacing the defaults
+    pub(crate) fn node(fields: Value) -> Window {
+        let mut node = json!({
+            "name": null, "id": 0, "type": "con", "focused": false, "floating": "auto_off",
+        });
+        if let (Some(node), Value::Object(fields)) = (node.as_object_mut(), fields) {
+            node.extend(fields);
+        }
+        serde_json::from_value(node).unwrap()
+    }
+
+    /// A window whose container and X11 ids are both `id`
+    pub(crate) fn window(id: u64, class: Option<&str>, title: &str) -> Window {
+        node(json!({
+            "name": title, "id": id, "window": id,
+            "window_properties": {"class": class},
+        }))
+    }
+
+    /// Workspace `1` holding `nodes`
+    pub(crate) fn workspace(layout: &str, nodes: Vec<Window>) -> Window {
+        node(json!({
+            "name": "1", "id": 1, "type": "workspace", "layout": layout,
+            "nodes": nodes, "floating_nodes": [],
+        }))
     }
 }
 
+/// The whole tree of i3, from the root down
+pub(crate) fn get_tree() -> Result<Window> {
+    let output = pipe(&mut [&mut cmd(&["i3-msg", "-t", "get_tree"])])?;
+    Ok(serde_json::from_str(&output)?)
+}
+
 // List all attached (not floating) windows
 pub(crate) fn list_attached_windows() -> Vec<Window> {
     let jq_final_cmd = format!("[.[] | {}]", WINDOW_PROPERTIES_SECTION);
@@ -185,6 +436,69 @@ mod tests {
         dbg!(floating_windows);
     }
 
+    /// Every node below `node`, floating ones included
+    fn descendants(node: &Window) -> Vec<&Window> {
+        let mut all = vec![node];
+        for child in node.nodes.iter().chain(&node.floating_nodes).flatten() {
+            all.extend(descendants(child));
+        }
+        all
+    }
+
+    #[test]
+    fn classify_recorded_tree() {
+        let root: Window = serde_json::from_str(include_str!("testdata/tree.json")).unwrap();
+        let nodes = descendants(&root);
+        let ids = |kind: NodeKind| -> Vec<u64> {
+            nodes
+                .iter()
+                .filter(|node| node.kind() == kind)
+                .map(|node| node.id % 100)
+                .collect()
+        };
+
+        // The unnamed terminal is a window, the named tabbed container isn't
+        assert_eq!(ids(NodeKind::Window), vec![6, 15, 16, 18, 20]);
+        assert_eq!(ids(NodeKind::Container), vec![3, 5, 12, 14, 17, 19]);
+        assert_eq!(ids(NodeKind::Dock), vec![22]);
+        assert_eq!(ids(NodeKind::DockArea), vec![11, 21]);
+        assert_eq!(ids(NodeKind::Workspace), vec![4, 13]);
+        assert_eq!(ids(NodeKind::Output), vec![2, 10]);
+
+        let workspace = nodes.iter().find(|node| node.id % 100 == 13).unwrap();
+        let leaves: Vec<u64> = workspace
+            .layout_leaves()
+            .iter()
+            .map(|(window, floating)| window.id % 100 + if *floating { 100 } else { 0 })
+            .collect();
+        assert_eq!(leaves, vec![15, 16, 18, 120]);
+    }
+
+    #[test]
+    fn snapshot_from_recorded_tree() {
+        let root: Window = serde_json::from_str(include_str!("testdata/tree.json")).unwrap();
+        let workspaces: Vec<Workspace> =
+            serde_json::from_value(serde_json::json!([{"id": 1, "name": "1", "output": "eDP-1"}]))
+                .unwrap();
+        let snapshot = Snapshot::from_tree(&root, workspaces);
+
+        assert_eq!(snapshot.floating_windows.len(), 4);
+        assert_eq!(snapshot.ws_trees["1"].id % 100, 13);
+        assert_eq!(snapshot.focused.as_ref().map(|w| w.id % 100), Some(15));
+        let docks: Vec<u64> = snapshot.docks.iter().map(|w| w.id % 100).collect();
+        assert_eq!(docks, vec![22]);
+        let windows: Vec<u64> = snapshot.workspace_windows()[0]
+            .1
+            .iter()
+            .map(|w| w.id % 100)
+            .collect();
+        assert_eq!(windows, vec![15, 16, 18]);
+        assert_eq!(
+            snapshot.ws_map_names["1"],
+            ["vim notes.md", "Mozilla Firefox"]
+        );
+    }
+
     #[test]
     fn list_ws() {
         dbg!(list_workspaces());
@@ -192,7 +506,7 @@ mod tests {
 
     #[test]
     fn list_ws_map() {
-        dbg!(list_workspaces_and_windows());
+        dbg!(Snapshot::collect().unwrap().ws_map);
     }
 }
 
@@ -203,58 +517,164 @@ pub(crate) fn list_windows() -> Vec<Window> {
     all_windows
 }
 
-pub(crate) fn get_workspace_window(workspace_name: &str) -> Window {
-    list_windows()
+/// Create a mapping from workspace name => the workspace container itself
+pub(crate) fn list_workspace_trees() -> Result<HashMap<String, Window>> {
+    Ok(Snapshot::collect()?.ws_trees)
+}
+
+/// The trees of the given workspaces, in their order
+pub(crate) fn ordered_trees<'a>(
+    workspaces: &'a [Workspace],
+    ws_trees: &'a HashMap<String, Window>,
+) -> Vec<(&'a str, &'a Window)> {
+    workspaces
         .iter()
-        .find(|w| w.name.as_ref().map_or(false, |name| name == workspace_name))
-        .unwrap()
-        .clone()
+        .filter_map(|workspace| {
+            ws_trees
+                .get(&workspace.name)
+                .map(|tree| (workspace.name.as_str(), tree))
+        })
+        .collect()
 }
 
-/// List at X Server windows whose type is workspace."""
-pub(crate) fn list_workspace_windows(workspace_name: &str) -> Vec<Window> {
-    let workspace_window = get_workspace_window(workspace_name);
-    workspace_window.nodes.unwrap_or(vec![])
+/// List the windows sent to the scratchpad
+pub(crate) fn list_scratchpad_windows() -> Result<Vec<Window>> {
+    let query = format!(
+        r#"[.. | select(.name? == "__i3_scratch") | .floating_nodes[] | .. | select(.window? != null) | {}]"#,
+        WINDOW_PROPERTIES_SECTION
+    );
+    let mut cmds = [
+        &mut cmd(&["i3-msg", "-t", "get_tree"]),
+        &mut cmd(&["jq", "-r", &query]),
+    ];
+
+    Ok(serde_json::from_str(&pipe(&mut cmds)?)?)
 }
 
 /// List all the workspaces that are managed by i3
 pub(crate) fn list_workspaces() -> Vec<Workspace> {
     let mut cmds = [
         &mut cmd(&["i3-msg", "-t", "get_workspaces"]),
-        &mut cmd(&["jq", "-r", "[.[] | {name, id}]"]),
+        &mut cmd(&[
+            "jq",
+            "-r",
+            "[.[] | {name, id, num, output, focused, visible, urgent}]",
+        ]),
     ];
 
     let output = pipe(&mut cmds).unwrap();
     serde_json::from_str::<Vec<Workspace>>(&output).unwrap()
 }
 
-/// Create a mapping from workspace id => Window
-pub(crate) fn list_workspaces_and_windows() -> HashMap<String, Vec<Window>> {
-    let workspaces = list_workspaces();
-
-    let mut map: HashMap<String, Vec<Window>> = HashMap::new();
-
-    for ws in workspaces {
-        map.insert(ws.name.clone(), list_workspace_windows(&ws.name));
+/// Move all windows from all workspaces to a single workspace.
+pub(crate) fn consolidate_workspaces(
+    ws_map: &HashMap<String, Vec<Window>>,
+    target_workspace: &str,
+) -> Result<()> {
+    for (workspace_name, workspace_nodes) in ws_map.iter() {
+        if workspace_name == target_workspace {
+            // Do nothing.
+        } else {
+            let nodes = workspace_nodes
+                .iter()
+                .flat_map(|window| window.flatten())
+                .filter(|w| w.is_window());
+            for node in nodes {
+                shutils::move_window_to_workspace(node.id, target_workspace)?;
+            }
+        }
     }
 
-    map
+    Ok(())
 }
 
-pub(crate) fn list_workspaces_and_window_names() -> HashMap<String, Vec<String>> {
-    let workspaces = list_workspaces();
+/// Everything we know about the i3 tree at a given point in time.
+///
+/// Collecting one takes a `get_tree` and a `get_workspaces`, and the daemon keeps one around
+/// to hand it out to the TUI and CLI.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct Snapshot {
+    pub(crate) windows: Vec<Window>,
+    pub(crate) attached_windows: Vec<Window>,
+    pub(crate) floating_windows: Vec<Window>,
+    pub(crate) workspaces: Vec<Workspace>,
+    pub(crate) ws_map: HashMap<String, Vec<Window>>,
+    pub(crate) ws_map_names: HashMap<String, Vec<String>>,
+    pub(crate) ws_trees: HashMap<String, Window>,
+    pub(crate) focused: Option<Window>,
+    /// Dock and bar windows, which belong to no workspace
+    #[serde(default)]
+    pub(crate) docks: Vec<Window>,
+}
 
-    let mut map: HashMap<String, Vec<String>> = HashMap::new();
-    for ws in workspaces {
-        let ws_name: String = ws.name.clone();
-        map.insert(ws_name, get_workspace_window(&ws.name).node_names());
+impl Snapshot {
+    /// Query i3 for the current state of the tree
+    pub(crate) fn collect() -> Result<Self> {
+        let root = get_tree().map_err(|err| format!("Couldn't retrieve the tree: {}", err))?;
+        Ok(Snapshot::from_tree(&root, list_workspaces()))
     }
 
-    map
-}
+    /// Everything there is to know, out of a single `get_tree`
+    pub(crate) fn from_tree(root: &Window, workspaces: Vec<Workspace>) -> Self {
+        let attached_windows = root.flatten();
+        let floating_windows: Vec<Window> = attached_windows
+            .iter()
+            .flat_map(|node| node.floating_nodes.iter().flatten())
+            .flat_map(|node| node.flatten())
+            .collect();
+        let mut windows = attached_windows.clone();
+        windows.extend(floating_windows.iter().cloned());
+
+        let ws_trees: HashMap<String, Window> = workspaces
+            .iter()
+            .filter_map(|ws| {
+                windows
+                    .iter()
+                    .find(|w| w.kind() == NodeKind::Workspace && w.name.as_ref() == Some(&ws.name))
+                    .map(|w| (ws.name.clone(), w.clone()))
+            })
+            .collect();
+        let ws_map = ws_trees
+            .iter()
+            .map(|(name, tree)| (name.clone(), tree.nodes.clone().unwrap_or_default()))
+            .collect();
+        let ws_map_names = ws_trees
+            .iter()
+            .map(|(name, tree)| (name.clone(), tree.node_names()))
+            .collect();
+
+        Snapshot {
+            focused: windows.iter().find(|window| window.focused).cloned(),
+            docks: windows
+                .iter()
+                .filter(|w| w.kind() == NodeKind::Dock)
+                .cloned()
+                .collect(),
+            windows,
+            attached_windows,
+            floating_windows,
+            workspaces,
+            ws_map,
+            ws_map_names,
+            ws_trees,
+        }
+    }
 
-/// Retrieve the currently focused window
-pub(crate) fn get_focused_window() -> Option<Window> {
-    let windows = list_windows();
-    windows.iter().find(|window| window.focused).cloned()
+    /// The windows of every workspace, in workspace order
+    pub(crate) fn workspace_windows(&self) -> Vec<(String, Vec<Window>)> {
+        self.workspaces
+            .iter()
+            .map(|ws| {
+                let windows = self
+                    .ws_map
+                    .get(&ws.name)
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|window| window.flatten())
+                    .filter(|w| w.is_window())
+                    .collect();
+                (ws.name.clone(), windows)
+            })
+            .collect()
+    }
 }
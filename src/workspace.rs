@@ -1,10 +1,200 @@
 /// Facilities for working withn i3 workspaces.
 ///
 use crate::prelude::*;
+use crate::state;
+use std::cmp::Ordering;
+
+const SORT_FILE: &str = "workspace_sort.json";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct Workspace {
     /// The actual XServer window id
     pub(crate) id: u64,
     pub(crate) name: String,
+    /// The number at the start of the name, -1 when there is none
+    #[serde(default)]
+    pub(crate) num: i64,
+    #[serde(default)]
+    pub(crate) output: String,
+    #[serde(default)]
+    pub(crate) focused: bool,
+    /// Shown on its output, though not necessarily focused
+    #[serde(default)]
+    pub(crate) visible: bool,
+    #[serde(default)]
+    pub(crate) urgent: bool,
+}
+
+impl Workspace {
+    /// One character telling whether the workspace is urgent, focused or visible
+    pub(crate) fn indicator(&self) -> char {
+        if self.urgent {
+            '!'
+        } else if self.focused {
+            '*'
+        } else if self.visible {
+            '+'
+        } else {
+            ' '
+        }
+    }
+}
+
+/// Orders in which the Workspaces pane lists the workspaces
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum WorkspaceSort {
+    /// By the number i3 parsed from the name, unnumbered workspaces last
+    #[default]
+    Number,
+    /// By name, comparing the runs of digits as numbers
+    Name,
+    /// By output, then by number
+    Output,
+    /// Most windows first
+    WindowCount,
+    /// Most recently focused first
+    LastFocused,
+}
+
+impl WorkspaceSort {
+    const ALL: [Self; 5] = [
+        WorkspaceSort::Number,
+        WorkspaceSort::Name,
+        WorkspaceSort::Output,
+        WorkspaceSort::WindowCount,
+        WorkspaceSort::LastFocused,
+    ];
+
+    /// Load the sort mode chosen during a previous session
+    pub(crate) fn load() -> Self {
+        state::load(SORT_FILE)
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        state::save(SORT_FILE, self)
+    }
+
+    pub(crate) fn next(&self) -> Self {
+        let position = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+        Self::ALL[(position + 1) % Self::ALL.len()]
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            WorkspaceSort::Number => "number",
+            WorkspaceSort::Name => "name",
+            WorkspaceSort::Output => "output",
+            WorkspaceSort::WindowCount => "windows",
+            WorkspaceSort::LastFocused => "recent",
+        }
+    }
+}
+
+/// Compare two names the way a human would, e.g. `2: web` before `10: chat`
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(ca), Some(cb)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+
+        let ordering = if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let end_a = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+            let end_b = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+            let (digits_a, digits_b) = (
+                a[..end_a].trim_start_matches('0'),
+                b[..end_b].trim_start_matches('0'),
+            );
+            let ordering = digits_a
+                .len()
+                .cmp(&digits_b.len())
+                .then_with(|| digits_a.cmp(digits_b));
+            (a, b) = (&a[end_a..], &b[end_b..]);
+            ordering
+        } else {
+            (a, b) = (&a[ca.len_utf8()..], &b[cb.len_utf8()..]);
+            ca.cmp(&cb)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Order of two i3 numbers, the unnumbered workspaces (-1) coming last
+fn num_cmp(a: &Workspace, b: &Workspace) -> Ordering {
+    (a.num < 0, a.num).cmp(&(b.num < 0, b.num))
+}
+
+/// Sort the workspaces. `window_count` and `last_focused` (lower is more recent) are looked up
+/// by workspace name, ties being broken by number then by name.
+pub(crate) fn sort_workspaces(
+    workspaces: &mut [Workspace],
+    mode: WorkspaceSort,
+    window_count: impl Fn(&str) -> usize,
+    last_focused: impl Fn(&str) -> Option<usize>,
+) {
+    workspaces.sort_by(|a, b| {
+        let ordering = match mode {
+            WorkspaceSort::Number => Ordering::Equal,
+            WorkspaceSort::Name => natural_cmp(&a.name, &b.name),
+            WorkspaceSort::Output => natural_cmp(&a.output, &b.output),
+            WorkspaceSort::WindowCount => window_count(&b.name).cmp(&window_count(&a.name)),
+            WorkspaceSort::LastFocused => {
+                let rank = |ws: &Workspace| last_focused(&ws.name).unwrap_or(usize::MAX);
+                rank(a).cmp(&rank(b))
+            }
+        };
+        ordering
+            .then_with(|| num_cmp(a, b))
+            .then_with(|| natural_cmp(&a.name, &b.name))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(name: &str, num: i64, output: &str) -> Workspace {
+        Workspace {
+            id: 0,
+            name: name.to_string(),
+            num,
+            output: output.to_string(),
+            focused: false,
+            visible: false,
+            urgent: false,
+        }
+    }
+
+    fn names(workspaces: &[Workspace]) -> Vec<&str> {
+        workspaces.iter().map(|ws| ws.name.as_str()).collect()
+    }
+
+    #[test]
+    fn sort_modes() {
+        assert_eq!(natural_cmp("ws2", "ws10"), Ordering::Less);
+        assert_eq!(natural_cmp("10: chat", "9: mail"), Ordering::Greater);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+
+        let mut workspaces = vec![
+            workspace("mail", -1, "HDMI-1"),
+            workspace("10: chat", 10, "eDP-1"),
+            workspace("2: web", 2, "HDMI-1"),
+            workspace("1", 1, "eDP-1"),
+        ];
+        let count = |name: &str| if name == "mail" { 3 } else { 1 };
+        let recent = |name: &str| (name == "10: chat").then_some(0);
+
+        sort_workspaces(&mut workspaces, WorkspaceSort::Number, count, recent);
+        assert_eq!(names(&workspaces), vec!["1", "2: web", "10: chat", "mail"]);
+        sort_workspaces(&mut workspaces, WorkspaceSort::Name, count, recent);
+        assert_eq!(names(&workspaces), vec!["1", "2: web", "10: chat", "mail"]);
+        sort_workspaces(&mut workspaces, WorkspaceSort::Output, count, recent);
+        assert_eq!(names(&workspaces), vec!["2: web", "mail", "1", "10: chat"]);
+        sort_workspaces(&mut workspaces, WorkspaceSort::WindowCount, count, recent);
+        assert_eq!(names(&workspaces), vec!["mail", "1", "2: web", "10: chat"]);
+        sort_workspaces(&mut workspaces, WorkspaceSort::LastFocused, count, recent);
+        assert_eq!(names(&workspaces), vec!["10: chat", "1", "2: web", "mail"]);
+    }
 }
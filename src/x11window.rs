@@ -1,39 +1,72 @@
 //! Facilities to work with X11 windows command line tools
 
 use crate::prelude::*;
-use crate::shutils::{cmd, pipe};
+use crate::shutils::cmd;
 use std::fs::File;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Retrieve the pixel dump of a given X window then write the content to a .pnm file.
-fn write_window_pixels(wid: u64, pnm_out: &str) -> Result<()> {
-    let file_out = File::create(pnm_out).unwrap();
-    let piped = Stdio::from(file_out);
-
-    let mut xwd_cmd = cmd(&["xwdtopnm"]);
-    xwd_cmd.stdout(piped);
-
-    let mut cmds = [
-        &mut cmd(&["xwd", "-id", &format!("0x{:x}", wid)]),
-        &mut xwd_cmd,
-    ];
-
-    // Execute the above commands piping them together
-    let _ = pipe(&mut cmds);
+fn write_window_pixels(wid: u64, pnm_out: File) -> Result<()> {
+    let mut xwd = cmd(&["xwd", "-id", &format!("0x{:x}", wid)])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let converted = cmd(&["xwdtopnm"])
+        .stdin(Stdio::from(xwd.stdout.take().unwrap()))
+        .stdout(Stdio::from(pnm_out))
+        .stderr(Stdio::null())
+        .status()?;
 
+    let xwd = xwd.wait_with_output()?;
+    if !xwd.status.success() {
+        let stderr = String::from_utf8_lossy(&xwd.stderr);
+        return Err(format!("xwd failed: {}", stderr.trim()).into());
+    }
+    if !converted.success() {
+        return Err(format!("xwdtopnm exited with {}", converted).into());
+    }
     Ok(())
 }
 
-fn read_window_pixels(pnm_file: &str) -> Result<image::DynamicImage> {
+fn read_window_pixels(pnm_file: &Path) -> Result<image::DynamicImage> {
     Ok(image::ImageReader::open(pnm_file)?.decode()?)
 }
 
-fn get_window_image(wid: u64) -> Result<image::DynamicImage> {
-    let tmp_file = format!("{}.ppm", wid);
+/// A file only we can read, under a name nobody can have guessed and claimed beforehand
+fn create_private_file(wid: u64) -> Result<(PathBuf, File)> {
+    let dir = match std::env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => std::env::temp_dir(),
+    };
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let path = dir.join(format!(
+        "i3-mgr-{}-{:x}-{}.ppm",
+        std::process::id(),
+        wid,
+        nanos
+    ));
+
+    // create_new refuses to follow a symlink planted at that path
+    let file = File::options()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    Ok((path, file))
+}
+
+/// Capture the content of an X window. It has to be mapped, i.e. shown on a visible workspace.
+pub(crate) fn get_window_image(wid: u64) -> Result<image::DynamicImage> {
+    let (tmp_file, file) = create_private_file(wid)?;
 
-    let _ = write_window_pixels(wid, &tmp_file)?;
-    let dyn_image = read_window_pixels(&tmp_file)?;
+    let dyn_image = write_window_pixels(wid, file).and_then(|()| read_window_pixels(&tmp_file));
 
-    // Now remove the temp file
-    std::fs::remove_file(tmp_file)?;
-    Ok(dyn_image)
+    // Now remove the temp file, whether the capture worked or not
+    let _ = std::fs::remove_file(&tmp_file);
+    dyn_image.map_err(|err| format!("Unable to capture window 0x{:x}: {}", wid, err).into())
 }